@@ -1,5 +1,18 @@
+pub mod effects;
+
+use std::collections::HashMap;
+
 use bevy::prelude::*;
 
+use crate::{
+    mathx,
+    player::{components::Eye, systems::move_player},
+    utils::{data_lines, expect_data, load_data},
+    AddUiMessageEvent, UserSettings,
+};
+
+use self::effects::*;
+
 #[derive(Component)]
 pub struct LowResCamera;
 
@@ -8,7 +21,34 @@ pub struct MainCamera;
 
 pub(crate) fn init(mut app: &mut App) {
     // app.add_event::<SetCameraModeEvent>();
-    app.add_systems(Update, camera_state_handler);
+    app.add_event::<PlayCameraSequenceEvent>();
+
+    app.add_systems(Startup, load_camera_sequences);
+    app.add_systems(
+        Update,
+        (
+            scene_look_toggle_system,
+            play_camera_sequence_listener,
+            camera_state_handler,
+        )
+            .chain(),
+    );
+
+    app.add_event::<CameraTraumaEvent>();
+    app.add_event::<ViewPunchEvent>();
+    app.init_resource::<CameraEffectsState>();
+
+    app.add_systems(
+        Update,
+        (
+            camera_effect_settings_system,
+            camera_effect_events_listener,
+            camera_effects_system,
+        )
+            .chain()
+            .after(move_player)
+            .after(camera_state_handler),
+    );
 }
 
 #[derive(Resource)]
@@ -16,6 +56,16 @@ pub(crate) struct CameraState {
     /// If scene_params is None, just be a FPS camera on the player.
     pub scene_params: Option<CameraSceneParams>,
     pub elapsed_time: f32,
+    /// Where the camera was when the scene started, the first keyframe eases from here.
+    pub start: Option<CameraPose>,
+    /// The player's yaw and pitch when the scene started, to tell how far they've looked since.
+    pub start_look: Vec2,
+    /// Where the blend back to the player's eyes starts, the last keyframe or wherever the
+    /// scene was skipped.
+    pub blend_from: Option<CameraPose>,
+    /// The player's own field of view, kept from outside any scene so one scene cutting
+    /// off another still blends back to it.
+    pub player_fov: Option<f32>,
 }
 
 impl Default for CameraState {
@@ -23,44 +73,405 @@ impl Default for CameraState {
         Self {
             scene_params: None,
             elapsed_time: 0.0,
+            start: None,
+            start_look: Vec2::ZERO,
+            blend_from: None,
+            player_fov: None,
         }
     }
 }
 
-#[derive(Copy, Clone)]
+impl CameraState {
+    /// Starts the named sequence around `target_position`, cutting off any scene already playing.
+    pub fn play(&mut self, sequence: &str, target_position: Vec3, scale: f32) {
+        self.scene_params = Some(CameraSceneParams {
+            sequence: String::from(sequence),
+            target_position,
+            scale,
+        });
+        self.elapsed_time = 0.0;
+        self.start = None;
+        self.blend_from = None;
+    }
+
+    /// True while the camera eases back to the player after the keyframes.
+    pub fn blending_out(&self) -> bool {
+        self.blend_from.is_some()
+    }
+}
+
+#[derive(Clone)]
 pub struct CameraSceneParams {
-    // pub entity: Entity,
+    pub sequence: String,
+    /// Keyframe positions and look-at points are relative to this.
     pub target_position: Vec3,
-    pub pos_offset: Vec3,
-    // pub rotation: Quat,
-    pub duration: f32,
+    /// Scales the keyframe offsets, so one sequence can frame a die or a whole room.
+    pub scale: f32,
+}
+
+/// Plays a camera sequence from `assets/camera/sequences.txt`.
+#[derive(Event)]
+pub struct PlayCameraSequenceEvent {
+    pub sequence: String,
+    pub target_position: Vec3,
+    pub scale: f32,
+}
+
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum Ease {
+    Linear,
+    In,
+    Out,
+    InOut,
+}
+
+impl Ease {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Ease::Linear => "linear",
+            Ease::In => "in",
+            Ease::Out => "out",
+            Ease::InOut => "in_out",
+        }
+    }
+
+    /// Maps linear progress `t` in 0..1 onto the curve.
+    pub fn apply(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Ease::Linear => t,
+            Ease::In => t * t * t,
+            Ease::Out => 1.0 - (1.0 - t).powi(3),
+            Ease::InOut => t * t * (3.0 - 2.0 * t),
+        }
+    }
+}
+
+/// Where the camera is, where it looks and how wide.
+#[derive(Clone, Copy, Debug)]
+pub struct CameraPose {
+    pub position: Vec3,
+    pub rotation: Quat,
+    /// Vertical field of view in radians.
+    pub fov: f32,
+}
+
+impl CameraPose {
+    pub fn lerp(&self, other: &CameraPose, t: f32) -> CameraPose {
+        CameraPose {
+            position: self.position.lerp(other.position, t),
+            rotation: self.rotation.slerp(other.rotation, t),
+            fov: self.fov + (other.fov - self.fov) * t,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CameraKeyframe {
+    pub position: Vec3,
+    pub look_at: Vec3,
+    /// Degrees, None keeps the field of view of the keyframe before.
+    pub fov: Option<f32>,
+    /// Seconds to get here from the keyframe before.
+    pub travel: f32,
+    /// Seconds to stay once here.
+    pub hold: f32,
+    pub ease: Ease,
+}
+
+#[derive(Clone, Debug)]
+pub struct CameraSequence {
+    pub keyframes: Vec<CameraKeyframe>,
+    /// Seconds to ease back into the player's eyes after the last keyframe.
+    pub blend_out: f32,
+    pub blend_ease: Ease,
+}
+
+impl CameraSequence {
+    /// Seconds until the last keyframe is done holding, not counting the blend out.
+    pub fn duration(&self) -> f32 {
+        self.keyframes.iter().map(|key| key.travel + key.hold).sum()
+    }
+
+    /// The pose `elapsed` seconds in, or None once the keyframes are done.
+    pub fn sample(
+        &self,
+        elapsed: f32,
+        start: CameraPose,
+        params: &CameraSceneParams,
+    ) -> Option<CameraPose> {
+        let mut from = start;
+        let mut time = elapsed;
+
+        for key in self.keyframes.iter() {
+            let to = self.pose(key, from.fov, params);
+
+            if time < key.travel {
+                return Some(from.lerp(&to, key.ease.apply(time / key.travel)));
+            }
+            time -= key.travel;
+
+            if time < key.hold {
+                return Some(to);
+            }
+            time -= key.hold;
+
+            from = to;
+        }
+
+        None
+    }
+
+    /// The pose of the last keyframe, where the blend out starts.
+    pub fn end_pose(&self, start: CameraPose, params: &CameraSceneParams) -> CameraPose {
+        self.keyframes
+            .iter()
+            .fold(start, |from, key| self.pose(key, from.fov, params))
+    }
+
+    fn pose(&self, key: &CameraKeyframe, fov: f32, params: &CameraSceneParams) -> CameraPose {
+        let position = params.target_position + key.position * params.scale;
+        let look_at = params.target_position + key.look_at * params.scale;
+
+        CameraPose {
+            position,
+            rotation: Transform::from_translation(position)
+                .looking_at(look_at, Vec3::Y)
+                .rotation,
+            fov: key.fov.map_or(fov, mathx::f32::degrees_to_radians),
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct CameraSequences {
+    pub sequences: HashMap<String, CameraSequence>,
+}
+
+impl CameraSequences {
+    pub fn load(path: &str) -> Result<Self, String> {
+        load_data(path, Self::parse)
+    }
+
+    /// Parses `sequence <name>` lines, each followed by its `key` lines and an optional
+    /// `blend_out` line. `#` starts a comment.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut sequences = Self::default();
+        let mut current: Option<String> = None;
+
+        for line in data_lines(text) {
+            let bad_line = || line.error();
+            let num = |word: &str| line.parse::<f32>(word);
+            let ease = |word: &str| {
+                [Ease::Linear, Ease::In, Ease::Out, Ease::InOut]
+                    .into_iter()
+                    .find(|ease| ease.name() == word)
+                    .ok_or_else(bad_line)
+            };
+
+            if let ["sequence", name] = line.words.as_slice() {
+                sequences.sequences.insert(
+                    String::from(*name),
+                    CameraSequence {
+                        keyframes: Vec::new(),
+                        blend_out: 0.0,
+                        blend_ease: Ease::InOut,
+                    },
+                );
+                current = Some(String::from(*name));
+                continue;
+            }
+
+            let sequence = current
+                .as_ref()
+                .and_then(|name| sequences.sequences.get_mut(name))
+                .ok_or_else(bad_line)?;
+
+            match line.words.as_slice() {
+                ["blend_out", seconds] => sequence.blend_out = num(seconds)?,
+                ["blend_out", seconds, curve] => {
+                    sequence.blend_out = num(seconds)?;
+                    sequence.blend_ease = ease(curve)?;
+                }
+                ["key", x, y, z, "look", lx, ly, lz, options @ ..] => {
+                    let mut key = CameraKeyframe {
+                        position: Vec3::new(num(x)?, num(y)?, num(z)?),
+                        look_at: Vec3::new(num(lx)?, num(ly)?, num(lz)?),
+                        fov: None,
+                        travel: 0.0,
+                        hold: 0.0,
+                        ease: Ease::InOut,
+                    };
+
+                    if options.len() % 2 != 0 {
+                        return Err(bad_line());
+                    }
+                    for pair in options.chunks(2) {
+                        match pair {
+                            ["fov", degrees] => key.fov = Some(num(degrees)?),
+                            ["travel", seconds] => key.travel = num(seconds)?,
+                            ["hold", seconds] => key.hold = num(seconds)?,
+                            ["ease", curve] => key.ease = ease(curve)?,
+                            _ => return Err(bad_line()),
+                        }
+                    }
+
+                    sequence.keyframes.push(key);
+                }
+                _ => return Err(bad_line()),
+            }
+        }
+
+        Ok(sequences)
+    }
+}
+
+fn load_camera_sequences(mut commands: Commands) {
+    let sequences = CameraSequences::load("assets/camera/sequences.txt");
+    commands.insert_resource(expect_data("camera sequences", sequences));
 }
 
+fn play_camera_sequence_listener(
+    mut camera_state: ResMut<CameraState>,
+    mut events: EventReader<PlayCameraSequenceEvent>,
+) {
+    for ev in events.read() {
+        camera_state.play(&ev.sequence, ev.target_position, ev.scale);
+    }
+}
+
+/// Flies the camera through the playing sequence, then eases it back to the player's eyes
+/// before handing it over. Any key skips straight to the blend.
 pub(crate) fn camera_state_handler(
-    mut query: Query<&mut Transform, With<LowResCamera>>,
+    mut query: Query<(&mut Transform, &mut Projection), With<LowResCamera>>,
+    eye_query: Query<&Eye>,
     mut camera_state: ResMut<CameraState>,
+    sequences: Res<CameraSequences>,
+    user_cfg: Res<UserSettings>,
+    key: Res<ButtonInput<KeyCode>>,
     time: Res<Time>,
 ) {
-    // Let the player system handle the camera
-    if camera_state.scene_params.is_none() {
+    let Ok((mut cam_xform, mut projection)) = query.get_single_mut() else {
         return;
+    };
+
+    // Let the player system handle the camera, scenes blend back to its field of view
+    let Some(params) = camera_state.scene_params.clone() else {
+        if let Projection::Perspective(perspective) = projection.as_ref() {
+            camera_state.player_fov = Some(perspective.fov);
+        }
+        return;
+    };
+
+    let Projection::Perspective(perspective) = projection.as_mut() else {
+        return;
+    };
+    let player_fov = *camera_state.player_fov.get_or_insert(perspective.fov);
+
+    let Some(sequence) = sequences.sequences.get(&params.sequence) else {
+        warn!("No camera sequence called {}", params.sequence);
+        camera_state.scene_params = None;
+        return;
+    };
+
+    let eye = eye_query.get_single().ok();
+    let look = eye.map_or(Vec2::ZERO, |eye| Vec2::new(eye.yaw, eye.pitch));
+
+    let start = match camera_state.start {
+        Some(start) => start,
+        None => {
+            let start = CameraPose {
+                position: cam_xform.translation,
+                rotation: cam_xform.rotation,
+                fov: perspective.fov,
+            };
+            camera_state.start = Some(start);
+            camera_state.start_look = look;
+            start
+        }
+    };
+
+    // Looking around during the scene turns the scene camera too, if the player wants
+    let looked = look - camera_state.start_look;
+    let look_around = |mut pose: CameraPose| {
+        if user_cfg.scene_look {
+            pose.rotation = Quat::from_axis_angle(Vec3::Y, looked.x)
+                * pose.rotation
+                * Quat::from_axis_angle(Vec3::X, looked.y);
+        }
+        pose
+    };
+
+    if !camera_state.blending_out() && key.get_just_pressed().next().is_some() {
+        camera_state.blend_from = Some(CameraPose {
+            position: cam_xform.translation,
+            rotation: cam_xform.rotation,
+            fov: perspective.fov,
+        });
+        camera_state.elapsed_time = sequence.duration();
     }
 
-    let mut cam_xform = query.single_mut();
-    let dt = time.delta_seconds();
+    camera_state.elapsed_time += time.delta_seconds();
+    let elapsed = camera_state.elapsed_time;
 
-    if let Some(info) = camera_state.scene_params {
-        cam_xform.translate_around(
-            info.target_position,
-            Quat::from_axis_angle(Vec3::Y, 0.6 * dt),
-        );
-        cam_xform.look_at(info.target_position, Vec3::Y);
-        camera_state.elapsed_time += dt;
+    let keyframe = match camera_state.blend_from {
+        Some(_) => None,
+        None => sequence.sample(elapsed, start, &params),
+    };
+
+    let pose = match keyframe {
+        Some(pose) => look_around(pose),
+        None => {
+            let from = *camera_state
+                .blend_from
+                .get_or_insert_with(|| look_around(sequence.end_pose(start, &params)));
 
-        if camera_state.elapsed_time >= info.duration {
-            camera_state.scene_params = None;
-            camera_state.elapsed_time = 0.0;
+            // The eye keeps following the player while the scene plays, so blend towards
+            // wherever it is now.
+            let to = eye.map_or(
+                CameraPose {
+                    fov: player_fov,
+                    ..start
+                },
+                |eye| CameraPose {
+                    position: eye.position,
+                    rotation: eye.view,
+                    fov: player_fov,
+                },
+            );
+            let blended = elapsed - sequence.duration();
+
+            if blended >= sequence.blend_out {
+                camera_state.scene_params = None;
+                camera_state.elapsed_time = 0.0;
+                camera_state.start = None;
+                camera_state.blend_from = None;
+                to
+            } else {
+                from.lerp(&to, sequence.blend_ease.apply(blended / sequence.blend_out))
+            }
         }
+    };
+
+    cam_xform.translation = pose.position;
+    cam_xform.rotation = pose.rotation;
+    perspective.fov = pose.fov;
+}
+
+fn scene_look_toggle_system(
+    key: Res<ButtonInput<KeyCode>>,
+    mut user_cfg: ResMut<UserSettings>,
+    mut add_message_event: EventWriter<AddUiMessageEvent>,
+) {
+    if key.just_pressed(KeyCode::F7) {
+        user_cfg.scene_look = !user_cfg.scene_look;
+        add_message_event.send(AddUiMessageEvent {
+            message: format!(
+                "Looking around during camera scenes {}",
+                if user_cfg.scene_look { "on" } else { "off" }
+            ),
+            duration: 1.5,
+        });
     }
 }
 
@@ -89,3 +500,80 @@ pub(crate) fn camera_state_handler(
 //         }
 //     }
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_keys_and_blend_out() {
+        let sequences = CameraSequences::parse(
+            "# a comment\n\
+             sequence orbit\n\
+             key 1 2 3 look 0 0 0 fov 90 hold 0.5   # first key\n\
+             key -1 2 3 look 0 1 0 travel 0.4 ease linear\n\
+             blend_out 0.5 in\n\
+             \n\
+             sequence plain\n\
+             key 0 0 1 look 0 0 0\n",
+        )
+        .unwrap();
+
+        let orbit = &sequences.sequences["orbit"];
+        assert_eq!(orbit.keyframes.len(), 2);
+        assert_eq!(orbit.keyframes[0].position, Vec3::new(1.0, 2.0, 3.0));
+        assert_eq!(orbit.keyframes[0].fov, Some(90.0));
+        assert_eq!(orbit.keyframes[0].hold, 0.5);
+        assert_eq!(orbit.keyframes[0].ease, Ease::InOut);
+        assert_eq!(orbit.keyframes[1].look_at, Vec3::new(0.0, 1.0, 0.0));
+        assert_eq!(orbit.keyframes[1].fov, None);
+        assert_eq!(orbit.keyframes[1].travel, 0.4);
+        assert_eq!(orbit.keyframes[1].ease, Ease::Linear);
+        assert_eq!(orbit.blend_out, 0.5);
+        assert_eq!(orbit.blend_ease, Ease::In);
+        assert!((orbit.duration() - 0.9).abs() < 1e-6);
+
+        let plain = &sequences.sequences["plain"];
+        assert_eq!(plain.keyframes.len(), 1);
+        assert_eq!(plain.blend_out, 0.0);
+        assert_eq!(plain.blend_ease, Ease::InOut);
+    }
+
+    #[test]
+    fn bad_lines_name_their_line() {
+        let bad = [
+            "key 0 0 1 look 0 0",
+            "key 0 0 one look 0 0 0",
+            "key 0 0 1 look 0 0 0 fov",
+            "key 0 0 1 look 0 0 0 zoom 2",
+            "key 0 0 1 look 0 0 0 ease bouncy",
+            "blend_out soon",
+            "sequence",
+        ];
+        for line in bad {
+            let text = format!("sequence test\n{}\n", line);
+            assert_eq!(
+                CameraSequences::parse(&text).err(),
+                Some(format!("line 2: can't read `{}`", line)),
+                "{}",
+                line
+            );
+        }
+    }
+
+    #[test]
+    fn keys_need_a_sequence() {
+        assert_eq!(
+            CameraSequences::parse("key 0 0 1 look 0 0 0\n").err(),
+            Some(String::from("line 1: can't read `key 0 0 1 look 0 0 0`"))
+        );
+    }
+
+    #[test]
+    fn shipped_sequences_parse() {
+        let sequences = CameraSequences::load("assets/camera/sequences.txt").unwrap();
+        for name in ["dice_closeup", "intro"] {
+            assert!(sequences.sequences.contains_key(name), "{}", name);
+        }
+    }
+}
@@ -0,0 +1,320 @@
+//! Procedural motion layered on the first-person camera after `move_player` puts it in the
+//! player's eyes: trauma shake, head bob, view punch and a dip on landing. Each has its own
+//! intensity and F8 turns them all off for players who get motion sick.
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use rand::Rng;
+
+use crate::{
+    combat::DamageEvent,
+    mathx,
+    player::components::Player,
+    rng::{GameRng, RngStream},
+    AddUiMessageEvent, UserSettings,
+};
+
+use super::{CameraState, LowResCamera};
+
+/// Trauma lost per second, shake goes with its square so it dies off quickly.
+const TRAUMA_DECAY: f32 = 1.2;
+const SHAKE_MAX_ANGLE: f32 = 3.0;
+const SHAKE_MAX_ROLL: f32 = 5.0;
+const SHAKE_MAX_OFFSET: f32 = 0.05;
+/// Trauma from losing this much health at once maxes out the shake.
+const DAMAGE_FOR_FULL_TRAUMA: f32 = 20.0;
+
+/// Bob cycles per metre walked, two steps each, and the speed the bob is at full height.
+/// Sprinting bobs no faster than walking.
+const BOB_FREQUENCY: f32 = 0.12;
+const BOB_FULL_SPEED: f32 = 10.0;
+const BOB_HEIGHT: f32 = 0.04;
+const BOB_SWAY: f32 = 0.025;
+
+/// Punch and dip spring back to rest, critically damped. A kick of `peak * sqrt(k) * e`
+/// tops out at `peak`.
+const SPRING_STIFFNESS: f32 = 150.0;
+/// Seconds in the air before landing dips the view, and metres dipped per second fallen.
+const DIP_MIN_AIR_TIME: f32 = 0.2;
+const DIP_PER_SECOND: f32 = 0.12;
+const DIP_MAX: f32 = 0.2;
+
+const INTENSITY_STEP: f32 = 0.25;
+const INTENSITY_MAX: f32 = 2.0;
+
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum CameraEffect {
+    Shake,
+    HeadBob,
+    ViewPunch,
+    LandingDip,
+}
+
+impl CameraEffect {
+    pub const ALL: [CameraEffect; 4] = [
+        CameraEffect::Shake,
+        CameraEffect::HeadBob,
+        CameraEffect::ViewPunch,
+        CameraEffect::LandingDip,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CameraEffect::Shake => "Screen shake",
+            CameraEffect::HeadBob => "Head bob",
+            CameraEffect::ViewPunch => "View punch",
+            CameraEffect::LandingDip => "Landing dip",
+        }
+    }
+}
+
+/// How strong each effect is, 1.0 as designed and 0.0 off.
+#[derive(Clone, Debug)]
+pub struct CameraEffectSettings {
+    pub enabled: bool,
+    pub intensities: [f32; 4],
+    /// The effect F9 picked for `[` and `]` to turn down and up.
+    pub selected: CameraEffect,
+}
+
+impl Default for CameraEffectSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            intensities: [1.0; 4],
+            selected: CameraEffect::Shake,
+        }
+    }
+}
+
+impl CameraEffectSettings {
+    /// 0.0 for everything while the effects are switched off.
+    pub fn intensity(&self, effect: CameraEffect) -> f32 {
+        if self.enabled {
+            self.intensities[effect as usize]
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Shakes the camera, `amount` of trauma from 0 to 1 is added to what's already there.
+#[derive(Event)]
+pub struct CameraTraumaEvent {
+    pub amount: f32,
+}
+
+/// Kicks the view by `pitch` and `yaw` degrees, it springs back on its own.
+#[derive(Event)]
+pub struct ViewPunchEvent {
+    pub pitch: f32,
+    pub yaw: f32,
+}
+
+#[derive(Resource, Default)]
+pub(crate) struct CameraEffectsState {
+    trauma: f32,
+    shake_time: f32,
+    bob_phase: f32,
+    bob_amount: f32,
+    /// Pitch and yaw in radians.
+    punch: Vec2,
+    punch_velocity: Vec2,
+    dip: f32,
+    dip_velocity: f32,
+    air_time: f32,
+    last_position: Option<Vec3>,
+}
+
+/// Moves `value` towards 0 on a critically damped spring.
+fn spring<T>(value: &mut T, velocity: &mut T, dt: f32)
+where
+    T: Copy
+        + std::ops::Mul<f32, Output = T>
+        + std::ops::Add<Output = T>
+        + std::ops::Sub<Output = T>
+        + std::ops::AddAssign,
+{
+    let damping = 2.0 * SPRING_STIFFNESS.sqrt();
+    let accel = *value * -SPRING_STIFFNESS - *velocity * damping;
+    *velocity += accel * dt;
+    *value += *velocity * dt;
+}
+
+pub(crate) fn camera_effect_settings_system(
+    key: Res<ButtonInput<KeyCode>>,
+    mut user_cfg: ResMut<UserSettings>,
+    mut add_message_event: EventWriter<AddUiMessageEvent>,
+) {
+    let settings = &mut user_cfg.camera_effects;
+
+    let message = if key.just_pressed(KeyCode::F8) {
+        settings.enabled = !settings.enabled;
+        format!(
+            "Camera motion {}",
+            if settings.enabled { "on" } else { "off" }
+        )
+    } else if key.just_pressed(KeyCode::F9) {
+        let next = CameraEffect::ALL
+            .iter()
+            .position(|effect| *effect == settings.selected)
+            .map_or(0, |i| (i + 1) % CameraEffect::ALL.len());
+        settings.selected = CameraEffect::ALL[next];
+        format!(
+            "{} {:.0}%, [ and ] to change",
+            settings.selected.name(),
+            settings.intensities[settings.selected as usize] * 100.0
+        )
+    } else if key.just_pressed(KeyCode::BracketLeft) || key.just_pressed(KeyCode::BracketRight) {
+        let step = if key.just_pressed(KeyCode::BracketLeft) {
+            -INTENSITY_STEP
+        } else {
+            INTENSITY_STEP
+        };
+        let intensity = &mut settings.intensities[settings.selected as usize];
+        *intensity = (*intensity + step).clamp(0.0, INTENSITY_MAX);
+        format!("{} {:.0}%", settings.selected.name(), *intensity * 100.0)
+    } else {
+        return;
+    };
+
+    add_message_event.send(AddUiMessageEvent {
+        message,
+        duration: 1.5,
+    });
+}
+
+/// Turns trauma and punch events, and the player getting hurt, into camera motion.
+pub(crate) fn camera_effect_events_listener(
+    mut state: ResMut<CameraEffectsState>,
+    player_query: Query<(), With<Player>>,
+    mut trauma_events: EventReader<CameraTraumaEvent>,
+    mut punch_events: EventReader<ViewPunchEvent>,
+    mut damage_events: EventReader<DamageEvent>,
+    mut rng: ResMut<GameRng>,
+) {
+    for ev in trauma_events.read() {
+        state.trauma = (state.trauma + ev.amount).clamp(0.0, 1.0);
+    }
+
+    for ev in punch_events.read() {
+        let punch = Vec2::new(
+            mathx::f32::degrees_to_radians(ev.pitch),
+            mathx::f32::degrees_to_radians(ev.yaw),
+        );
+        // Kick the spring rather than the angle, so the view snaps over and eases back.
+        state.punch_velocity += punch * SPRING_STIFFNESS.sqrt() * std::f32::consts::E;
+    }
+
+    for ev in damage_events.read() {
+        if ev.amount <= 0 || !player_query.contains(ev.target) {
+            continue;
+        }
+
+        let hurt = (ev.amount as f32 / DAMAGE_FOR_FULL_TRAUMA).min(1.0);
+        state.trauma = (state.trauma + 0.3 + hurt * 0.5).min(1.0);
+
+        let rng = rng.stream(RngStream::Cosmetic);
+        let yaw = mathx::f32::degrees_to_radians(rng.gen_range(-4.0..4.0));
+        let pitch = mathx::f32::degrees_to_radians(3.0 + 4.0 * hurt);
+        state.punch_velocity +=
+            Vec2::new(pitch, yaw) * SPRING_STIFFNESS.sqrt() * std::f32::consts::E;
+    }
+}
+
+/// Layers the effects on top of the eye pose `move_player` just wrote. Camera scenes own the
+/// camera, so nothing is added while one plays.
+pub(crate) fn camera_effects_system(
+    mut cam_query: Query<&mut Transform, With<LowResCamera>>,
+    player_query: Query<
+        (&Transform, Option<&KinematicCharacterControllerOutput>),
+        (With<Player>, Without<LowResCamera>),
+    >,
+    mut state: ResMut<CameraEffectsState>,
+    camera_state: Res<CameraState>,
+    user_cfg: Res<UserSettings>,
+    time: Res<Time>,
+) {
+    let dt = time.delta_seconds();
+    if dt <= 0.0 {
+        return;
+    }
+
+    let Ok((player_xform, output)) = player_query.get_single() else {
+        return;
+    };
+    let Ok(mut cam_xform) = cam_query.get_single_mut() else {
+        return;
+    };
+
+    let state = &mut *state;
+    let settings = &user_cfg.camera_effects;
+    let grounded = output.map_or(true, |output| output.grounded);
+
+    // Head bob follows how fast the player actually moved, not how hard they pushed.
+    let moved = player_xform.translation - state.last_position.unwrap_or(player_xform.translation);
+    state.last_position = Some(player_xform.translation);
+    let speed = Vec2::new(moved.x, moved.z).length() / dt;
+
+    let bob_target = if grounded {
+        (speed / BOB_FULL_SPEED).min(1.0)
+    } else {
+        0.0
+    };
+    state.bob_amount += (bob_target - state.bob_amount) * (dt * 8.0).min(1.0);
+    state.bob_phase += speed.min(BOB_FULL_SPEED) * BOB_FREQUENCY * std::f32::consts::TAU * dt;
+
+    // Dip on landing, deeper the longer the fall
+    if grounded {
+        if state.air_time > DIP_MIN_AIR_TIME {
+            let dip = (state.air_time * DIP_PER_SECOND).min(DIP_MAX);
+            state.dip_velocity -= dip * SPRING_STIFFNESS.sqrt() * std::f32::consts::E;
+        }
+        state.air_time = 0.0;
+    } else {
+        state.air_time += dt;
+    }
+
+    state.trauma = (state.trauma - TRAUMA_DECAY * dt).max(0.0);
+    state.shake_time += dt;
+
+    spring(&mut state.punch, &mut state.punch_velocity, dt);
+    spring(&mut state.dip, &mut state.dip_velocity, dt);
+
+    if camera_state.scene_params.is_some() {
+        return;
+    }
+
+    // Sines at unrelated frequencies stand in for noise, so the shake never repeats visibly.
+    let shake = state.trauma * state.trauma * settings.intensity(CameraEffect::Shake);
+    let t = state.shake_time;
+    let wobble = |a: f32, b: f32| ((t * a).sin() + (t * b + 1.3).sin()) * 0.5;
+    let shake_angles = Vec3::new(
+        mathx::f32::degrees_to_radians(SHAKE_MAX_ANGLE) * wobble(23.0, 37.0),
+        mathx::f32::degrees_to_radians(SHAKE_MAX_ANGLE) * wobble(29.0, 17.0),
+        mathx::f32::degrees_to_radians(SHAKE_MAX_ROLL) * wobble(19.0, 31.0),
+    ) * shake;
+    let shake_offset =
+        Vec3::new(wobble(41.0, 13.0), wobble(11.0, 43.0), 0.0) * SHAKE_MAX_OFFSET * shake;
+
+    let bob = state.bob_amount * settings.intensity(CameraEffect::HeadBob);
+    let bob_offset = Vec3::new(
+        state.bob_phase.sin() * BOB_SWAY,
+        state.bob_phase.sin().abs() * -BOB_HEIGHT,
+        0.0,
+    ) * bob;
+
+    let punch = state.punch * settings.intensity(CameraEffect::ViewPunch);
+    let dip = state.dip * settings.intensity(CameraEffect::LandingDip);
+
+    let local_offset = shake_offset + bob_offset;
+    let offset = cam_xform.rotation * local_offset + Vec3::Y * dip;
+    cam_xform.translation += offset;
+    cam_xform.rotation = cam_xform.rotation
+        * Quat::from_euler(
+            EulerRot::YXZ,
+            punch.y + shake_angles.y,
+            punch.x + shake_angles.x,
+            shake_angles.z,
+        );
+}
@@ -0,0 +1,642 @@
+use bevy::{color::palettes::tailwind, prelude::*};
+use rand::{seq::SliceRandom, Rng};
+
+use crate::{
+    combat::{DamageEvent, Health},
+    enemy::Enemy,
+    player::components::{Inventory, Player, MAX_DICE_CARRIED},
+    rng::{GameRng, RngStream},
+    utils::{data_lines, expect_data, load_data},
+    AddUiMessageEvent, GameResourceHandles, GameUi,
+};
+
+/// The card atlas is a 15 x 10 grid of 32 px tiles. The four suits fill the first four
+/// rows, ace to king.
+pub const ATLAS_COLUMNS: u32 = 15;
+pub const ATLAS_ROWS: u32 = 10;
+pub const ATLAS_TILE: u32 = 32;
+const CARD_BACK_INDEX: usize = 29;
+
+/// Most cards the player can hold.
+const MAX_HAND: usize = 5;
+/// On-screen size of a card in the hand, in pixels.
+const CARD_SIZE: f32 = 96.0;
+
+#[derive(Eq, PartialEq, Hash, Clone, Copy, Debug)]
+pub enum Suit {
+    Hearts,
+    Diamonds,
+    Clubs,
+    Spades,
+}
+
+impl Suit {
+    pub const ALL: [Suit; 4] = [Suit::Hearts, Suit::Diamonds, Suit::Clubs, Suit::Spades];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Suit::Hearts => "hearts",
+            Suit::Diamonds => "diamonds",
+            Suit::Clubs => "clubs",
+            Suit::Spades => "spades",
+        }
+    }
+}
+
+/// A playing card, one entity per card in a [`Deck`].
+#[derive(Component, Eq, PartialEq, Hash, Clone, Copy, Debug)]
+pub struct Card {
+    pub suit: Suit,
+    /// 1 for an ace up to 13 for a king.
+    pub rank: u8,
+}
+
+impl Card {
+    pub fn rank_name(rank: u8) -> String {
+        match rank {
+            1 => String::from("ace"),
+            11 => String::from("jack"),
+            12 => String::from("queen"),
+            13 => String::from("king"),
+            _ => rank.to_string(),
+        }
+    }
+
+    pub fn name(&self) -> String {
+        format!("{} of {}", Card::rank_name(self.rank), self.suit.name())
+    }
+
+    pub fn atlas_index(&self) -> usize {
+        (self.suit as u32 * ATLAS_COLUMNS) as usize + self.rank as usize - 1
+    }
+}
+
+/// Someone's cards, split between the draw pile, their hand and the discards.
+#[derive(Component, Default)]
+pub struct Deck {
+    /// The top card is the last one.
+    pub draw_pile: Vec<Entity>,
+    pub hand: Vec<Entity>,
+    pub discard_pile: Vec<Entity>,
+    /// Index into `hand` of the card V plays.
+    pub selected: usize,
+}
+
+impl Deck {
+    pub fn shuffle(&mut self, rng: &mut impl Rng) {
+        self.draw_pile.shuffle(rng);
+    }
+
+    /// Moves the top card into the hand, shuffling the discards back in first if the
+    /// draw pile has run out. `None` once every card is in the hand.
+    pub fn draw(&mut self, rng: &mut impl Rng) -> Option<Entity> {
+        if self.draw_pile.is_empty() {
+            self.draw_pile.append(&mut self.discard_pile);
+            self.shuffle(rng);
+        }
+
+        let card = self.draw_pile.pop()?;
+        self.hand.push(card);
+        Some(card)
+    }
+
+    /// Moves the card at `index` of the hand onto the discard pile.
+    pub fn discard(&mut self, index: usize) -> Option<Entity> {
+        if index >= self.hand.len() {
+            return None;
+        }
+
+        let card = self.hand.remove(index);
+        self.discard_pile.push(card);
+        self.selected = self.selected.min(self.hand.len().saturating_sub(1));
+        Some(card)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum CardEffect {
+    Heal {
+        amount: i32,
+    },
+    Gold {
+        amount: u32,
+    },
+    Dice {
+        amount: u32,
+    },
+    /// Hurts every enemy within `radius` of whoever played it.
+    Smite {
+        radius: f32,
+        damage: i32,
+    },
+    /// Draws more cards.
+    Draw {
+        count: u32,
+    },
+    /// Only shows its message, for events that read [`CardPlayedEvent`] themselves.
+    Event {
+        message: String,
+    },
+}
+
+/// Matches a card by rank and suit, `None` matching any.
+#[derive(Clone, Debug)]
+pub struct CardRule {
+    pub rank: Option<u8>,
+    pub suit: Option<Suit>,
+    pub effect: CardEffect,
+}
+
+/// What each card does when played, read from `cards/effects.txt`.
+#[derive(Resource, Default)]
+pub struct CardEffects {
+    pub rules: Vec<CardRule>,
+}
+
+impl CardEffects {
+    pub fn load(path: &str) -> Result<Self, String> {
+        load_data(path, Self::parse)
+    }
+
+    /// Parses `card <rank> <suit> <effect> <args..>` lines, where rank and suit can be `*`.
+    /// `#` starts a comment.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut effects = Self::default();
+
+        for line in data_lines(text) {
+            let bad_line = || line.error();
+            let num = |word: &str| line.parse::<f32>(word);
+            let int = |word: &str| line.parse::<i32>(word);
+            let count = |word: &str| line.parse::<u32>(word);
+
+            let ["card", rank, suit, effect @ ..] = line.words.as_slice() else {
+                return Err(bad_line());
+            };
+
+            let rank = match *rank {
+                "*" => None,
+                "a" => Some(1),
+                "j" => Some(11),
+                "q" => Some(12),
+                "k" => Some(13),
+                number => match number.parse::<u8>() {
+                    Ok(rank) if (2..=10).contains(&rank) => Some(rank),
+                    _ => return Err(bad_line()),
+                },
+            };
+            let suit = match *suit {
+                "*" => None,
+                name => Some(
+                    Suit::ALL
+                        .into_iter()
+                        .find(|suit| suit.name() == name)
+                        .ok_or_else(bad_line)?,
+                ),
+            };
+
+            let effect = match effect {
+                ["heal", amount] => CardEffect::Heal {
+                    amount: int(amount)?,
+                },
+                ["gold", amount] => CardEffect::Gold {
+                    amount: count(amount)?,
+                },
+                ["dice", amount] => CardEffect::Dice {
+                    amount: count(amount)?,
+                },
+                ["smite", radius, damage] => CardEffect::Smite {
+                    radius: num(radius)?,
+                    damage: int(damage)?,
+                },
+                ["draw", amount] => CardEffect::Draw {
+                    count: count(amount)?,
+                },
+                ["event", message @ ..] if !message.is_empty() => CardEffect::Event {
+                    message: message.join(" "),
+                },
+                _ => return Err(bad_line()),
+            };
+
+            effects.rules.push(CardRule { rank, suit, effect });
+        }
+
+        Ok(effects)
+    }
+
+    /// The effect of the first rule matching `card`.
+    pub fn effect(&self, card: Card) -> Option<&CardEffect> {
+        self.rules
+            .iter()
+            .find(|rule| {
+                rule.rank.map_or(true, |rank| rank == card.rank)
+                    && rule.suit.map_or(true, |suit| suit == card.suit)
+            })
+            .map(|rule| &rule.effect)
+    }
+}
+
+#[derive(Event)]
+pub struct DrawCardEvent {
+    pub owner: Entity,
+    pub count: u32,
+}
+
+/// Plays the card at `index` of the owner's hand.
+#[derive(Event)]
+pub struct PlayCardEvent {
+    pub owner: Entity,
+    pub index: usize,
+}
+
+#[derive(Event)]
+pub struct CardDrawnEvent {
+    pub owner: Entity,
+    pub card: Card,
+}
+
+#[derive(Event)]
+pub struct CardPlayedEvent {
+    pub owner: Entity,
+    pub card: Card,
+}
+
+#[derive(Component)]
+struct HandUi;
+
+pub(crate) fn init(app: &mut App) {
+    app.add_event::<DrawCardEvent>();
+    app.add_event::<PlayCardEvent>();
+    app.add_event::<CardDrawnEvent>();
+    app.add_event::<CardPlayedEvent>();
+
+    app.add_systems(Startup, load_card_effects);
+    app.add_systems(
+        Update,
+        (
+            create_deck_system,
+            card_input_system,
+            play_card_listener,
+            draw_card_listener,
+            hand_ui_system,
+        )
+            .chain(),
+    );
+}
+
+fn load_card_effects(mut commands: Commands) {
+    let effects = CardEffects::load("assets/cards/effects.txt");
+    commands.insert_resource(expect_data("card effects", effects));
+}
+
+/// Gives the player a shuffled 52 card deck.
+fn create_deck_system(
+    mut commands: Commands,
+    query: Query<Entity, Added<Player>>,
+    mut rng: ResMut<GameRng>,
+) {
+    for owner in query.iter() {
+        let mut deck = Deck::default();
+
+        for suit in Suit::ALL {
+            for rank in 1..=13 {
+                deck.draw_pile
+                    .push(commands.spawn(Card { suit, rank }).id());
+            }
+        }
+
+        deck.shuffle(rng.stream(RngStream::Cards));
+        commands.entity(owner).insert(deck);
+    }
+}
+
+/// C draws a card, B picks the next card in the hand and V plays it.
+fn card_input_system(
+    mut query: Query<(Entity, &mut Deck), With<Player>>,
+    key: Res<ButtonInput<KeyCode>>,
+    mut draw_events: EventWriter<DrawCardEvent>,
+    mut play_events: EventWriter<PlayCardEvent>,
+    mut add_message_event: EventWriter<AddUiMessageEvent>,
+) {
+    let Ok((owner, mut deck)) = query.get_single_mut() else {
+        return;
+    };
+
+    if key.just_pressed(KeyCode::KeyC) {
+        if deck.hand.len() >= MAX_HAND {
+            add_message_event.send(AddUiMessageEvent {
+                message: String::from("Your hand is full"),
+                duration: 1.5,
+            });
+        } else {
+            draw_events.send(DrawCardEvent { owner, count: 1 });
+        }
+    }
+
+    if key.just_pressed(KeyCode::KeyB) && !deck.hand.is_empty() {
+        deck.selected = (deck.selected + 1) % deck.hand.len();
+    }
+
+    if key.just_pressed(KeyCode::KeyV) && !deck.hand.is_empty() {
+        play_events.send(PlayCardEvent {
+            owner,
+            index: deck.selected,
+        });
+    }
+}
+
+fn draw_card_listener(
+    mut query: Query<&mut Deck>,
+    card_query: Query<&Card>,
+    mut rng: ResMut<GameRng>,
+    mut events: EventReader<DrawCardEvent>,
+    mut drawn_events: EventWriter<CardDrawnEvent>,
+    mut add_message_event: EventWriter<AddUiMessageEvent>,
+) {
+    for ev in events.read() {
+        let Ok(mut deck) = query.get_mut(ev.owner) else {
+            continue;
+        };
+
+        for _ in 0..ev.count {
+            if deck.hand.len() >= MAX_HAND {
+                break;
+            }
+            let Some(card) = deck
+                .draw(rng.stream(RngStream::Cards))
+                .and_then(|card| card_query.get(card).ok())
+            else {
+                break;
+            };
+
+            add_message_event.send(AddUiMessageEvent {
+                message: format!("You draw the {}", card.name()),
+                duration: 2.0,
+            });
+            drawn_events.send(CardDrawnEvent {
+                owner: ev.owner,
+                card: *card,
+            });
+        }
+    }
+}
+
+fn play_card_listener(
+    mut query: Query<(
+        &mut Deck,
+        &Transform,
+        Option<&mut Health>,
+        Option<&mut Inventory>,
+    )>,
+    card_query: Query<&Card>,
+    enemy_query: Query<(Entity, &Transform), (With<Enemy>, Without<Deck>)>,
+    effects: Res<CardEffects>,
+    mut events: EventReader<PlayCardEvent>,
+    mut draw_events: EventWriter<DrawCardEvent>,
+    mut damage_events: EventWriter<DamageEvent>,
+    mut played_events: EventWriter<CardPlayedEvent>,
+    mut add_message_event: EventWriter<AddUiMessageEvent>,
+) {
+    for ev in events.read() {
+        let Ok((mut deck, xform, health, inventory)) = query.get_mut(ev.owner) else {
+            continue;
+        };
+        let Some(card) = deck
+            .discard(ev.index)
+            .and_then(|card| card_query.get(card).ok())
+            .copied()
+        else {
+            continue;
+        };
+
+        let message = match effects.effect(card) {
+            Some(CardEffect::Heal { amount }) => {
+                if let Some(mut health) = health {
+                    health.current = (health.current + amount).min(health.max);
+                }
+                format!("healed {}", amount)
+            }
+            Some(CardEffect::Gold { amount }) => {
+                if let Some(mut inventory) = inventory {
+                    inventory.gold += amount;
+                }
+                format!("{} gold", amount)
+            }
+            Some(CardEffect::Dice { amount }) => {
+                if let Some(mut inventory) = inventory {
+                    inventory.dice = (inventory.dice + amount).min(MAX_DICE_CARRIED);
+                }
+                format!("{} dice", amount)
+            }
+            Some(CardEffect::Smite { radius, damage }) => {
+                for (enemy, enemy_xform) in enemy_query.iter() {
+                    if enemy_xform.translation.distance(xform.translation) <= *radius {
+                        damage_events.send(DamageEvent {
+                            target: enemy,
+                            amount: *damage,
+                            source: Some(ev.owner),
+                        });
+                    }
+                }
+                String::from("lightning strikes!")
+            }
+            Some(CardEffect::Draw { count }) => {
+                draw_events.send(DrawCardEvent {
+                    owner: ev.owner,
+                    count: *count,
+                });
+                format!("draw {}", count)
+            }
+            Some(CardEffect::Event { message }) => message.clone(),
+            None => String::from("nothing happens"),
+        };
+
+        add_message_event.send(AddUiMessageEvent {
+            message: format!("The {}: {}", card.name(), message),
+            duration: 2.5,
+        });
+        played_events.send(CardPlayedEvent {
+            owner: ev.owner,
+            card,
+        });
+    }
+}
+
+/// Draws the pile and the player's hand along the bottom of the screen, rebuilt whenever
+/// the deck changes.
+fn hand_ui_system(
+    mut commands: Commands,
+    deck_query: Query<Ref<Deck>, With<Player>>,
+    card_query: Query<&Card>,
+    hand_query: Query<Entity, With<HandUi>>,
+    ui: Res<GameUi>,
+    resources: Res<GameResourceHandles>,
+) {
+    let Ok(deck) = deck_query.get_single() else {
+        return;
+    };
+    let Some(root) = ui.ui_entity else {
+        return;
+    };
+    if !deck.is_changed() && !hand_query.is_empty() {
+        return;
+    }
+
+    for entity in hand_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let card_image = |index: usize, raised: bool| {
+        (
+            ImageBundle {
+                style: Style {
+                    width: Val::Px(CARD_SIZE),
+                    height: Val::Px(CARD_SIZE),
+                    margin: UiRect::bottom(Val::Px(if raised { 24.0 } else { 0.0 })),
+                    ..default()
+                },
+                image: UiImage::new(resources.card_atlas.clone()),
+                ..default()
+            },
+            TextureAtlas {
+                layout: resources.card_layout.clone(),
+                index,
+            },
+        )
+    };
+
+    let hand = commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(8.0),
+                left: Val::Px(8.0),
+                align_items: AlignItems::FlexEnd,
+                column_gap: Val::Px(4.0),
+                ..default()
+            },
+            ..default()
+        })
+        .insert(HandUi)
+        .with_children(|parent| {
+            if !deck.draw_pile.is_empty() {
+                parent.spawn(card_image(CARD_BACK_INDEX, false));
+            }
+            parent.spawn(TextBundle::from_section(
+                format!("{}", deck.draw_pile.len()),
+                TextStyle {
+                    font: resources.font.clone(),
+                    font_size: 20.0,
+                    color: tailwind::YELLOW_100.into(),
+                },
+            ));
+
+            for (i, card) in deck.hand.iter().enumerate() {
+                if let Ok(card) = card_query.get(*card) {
+                    parent.spawn(card_image(card.atlas_index(), i == deck.selected));
+                }
+            }
+        })
+        .id();
+
+    commands.entity(root).add_child(hand);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn card(rank: u8, suit: Suit) -> Card {
+        Card { suit, rank }
+    }
+
+    #[test]
+    fn parses_every_effect() {
+        let effects = CardEffects::parse(
+            "card a spades smite 8 20\n\
+             card 7 * gold 5\n\
+             card * hearts heal 3\n\
+             card k * dice 2\n\
+             card q clubs draw 2\n\
+             card j * event The knave grins.\n",
+        )
+        .unwrap();
+
+        let expected = [
+            CardEffect::Smite {
+                radius: 8.0,
+                damage: 20,
+            },
+            CardEffect::Gold { amount: 5 },
+            CardEffect::Heal { amount: 3 },
+            CardEffect::Dice { amount: 2 },
+            CardEffect::Draw { count: 2 },
+            CardEffect::Event {
+                message: String::from("The knave grins."),
+            },
+        ];
+        let parsed: Vec<CardEffect> = effects.rules.iter().map(|r| r.effect.clone()).collect();
+        assert_eq!(parsed, expected);
+
+        assert_eq!(effects.rules[0].rank, Some(1));
+        assert_eq!(effects.rules[0].suit, Some(Suit::Spades));
+        assert_eq!(effects.rules[1].rank, Some(7));
+        assert_eq!(effects.rules[1].suit, None);
+        assert_eq!(effects.rules[2].rank, None);
+        assert_eq!(effects.rules[3].rank, Some(13));
+        assert_eq!(effects.rules[4].rank, Some(12));
+        assert_eq!(effects.rules[5].rank, Some(11));
+    }
+
+    #[test]
+    fn first_matching_rule_wins() {
+        let effects = CardEffects::parse(
+            "# special cards first\n\
+             card a spades smite 8 20   # the big one\n\
+             \n\
+             card * spades heal 1\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            effects.effect(card(1, Suit::Spades)),
+            Some(&CardEffect::Smite {
+                radius: 8.0,
+                damage: 20
+            })
+        );
+        assert_eq!(
+            effects.effect(card(2, Suit::Spades)),
+            Some(&CardEffect::Heal { amount: 1 })
+        );
+        assert_eq!(effects.effect(card(1, Suit::Hearts)), None);
+    }
+
+    #[test]
+    fn bad_lines_name_their_line() {
+        let bad = [
+            "card 1 hearts heal 3",
+            "card 11 hearts heal 3",
+            "card a cups heal 3",
+            "card a hearts heal",
+            "card a hearts heal lots",
+            "card a hearts gold -5",
+            "card a hearts event",
+            "card a hearts fly 3",
+            "deck a hearts heal 3",
+        ];
+        for line in bad {
+            let text = format!("card * * heal 1\n{}\n", line);
+            assert_eq!(
+                CardEffects::parse(&text).err(),
+                Some(format!("line 2: can't read `{}`", line)),
+                "{}",
+                line
+            );
+        }
+    }
+
+    #[test]
+    fn shipped_effects_parse() {
+        CardEffects::load("assets/cards/effects.txt").unwrap();
+    }
+}
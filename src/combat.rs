@@ -0,0 +1,349 @@
+use std::{
+    collections::HashMap,
+    sync::{Mutex, OnceLock},
+};
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use rand::Rng;
+
+use crate::{
+    dice::notation::DiceExpr,
+    encounter::{exploring, EncounterStartedEvent},
+    enemy::Enemy,
+    rng::{GameRng, RngStream},
+    AddUiMessageEvent, GameResourceHandles,
+};
+
+#[derive(Component)]
+pub struct Health {
+    pub current: i32,
+    pub max: i32,
+}
+
+impl Health {
+    pub fn new(max: i32) -> Self {
+        Self { current: max, max }
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.current <= 0
+    }
+}
+
+/// What a to-hit roll has to reach to land in a turn-based encounter.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Armour(pub i32);
+
+/// A damage roll in dice notation, e.g. `1d4+1`. See [`crate::dice::notation`].
+#[derive(Copy, Clone, Debug)]
+pub struct DamageRoll {
+    pub notation: &'static str,
+}
+
+impl DamageRoll {
+    pub const fn new(notation: &'static str) -> Self {
+        Self { notation }
+    }
+
+    /// Every roll the game defines is checked by `every_damage_roll_parses`.
+    pub fn parse(&self) -> Result<DiceExpr, String> {
+        DiceExpr::parse(self.notation)
+            .map_err(|e| format!("Bad damage roll `{}`: {}", self.notation, e))
+    }
+
+    /// The parsed roll. Each notation is only parsed the first time it's rolled.
+    pub fn expr(&self) -> DiceExpr {
+        self.with_expr(DiceExpr::clone)
+    }
+
+    pub fn roll(&self, rng: &mut impl Rng) -> i32 {
+        self.with_expr(|expr| expr.roll(rng).total.max(0))
+    }
+
+    fn with_expr<T>(&self, f: impl FnOnce(&DiceExpr) -> T) -> T {
+        static PARSED: OnceLock<Mutex<HashMap<&'static str, DiceExpr>>> = OnceLock::new();
+
+        let mut parsed = PARSED.get_or_init(Default::default).lock().unwrap();
+        let expr = parsed
+            .entry(self.notation)
+            .or_insert_with(|| self.parse().unwrap());
+        f(expr)
+    }
+}
+
+#[derive(Event)]
+pub struct DamageEvent {
+    pub target: Entity,
+    pub amount: i32,
+    pub source: Option<Entity>,
+}
+
+/// Marks an entity that died this frame. It is despawned in `PostUpdate`, so
+/// `DeathEvent` readers ordered after `damage_listener` can still inspect it.
+#[derive(Component)]
+pub struct Dead;
+
+#[derive(Event)]
+pub struct DeathEvent {
+    pub entity: Entity,
+    pub position: Vec3,
+}
+
+#[derive(Component)]
+pub struct Projectile {
+    pub owner: Entity,
+    pub damage: DamageRoll,
+    pub life_time: f32,
+    pub max_life_time: f32,
+}
+
+/// Steers a projectile's velocity towards `target`, keeping its speed.
+#[derive(Component)]
+pub struct Homing {
+    pub target: Entity,
+    /// Radians per second.
+    pub turn_rate: f32,
+}
+
+#[derive(Bundle)]
+pub struct ProjectileBundle {
+    pub projectile: Projectile,
+    pub rb: RigidBody,
+    pub collider: Collider,
+    pub sensor: Sensor,
+    pub velocity: Velocity,
+    pub gravity: GravityScale,
+    pub ccd: Ccd,
+    pub collision_events: ActiveEvents,
+}
+
+impl ProjectileBundle {
+    pub fn new(owner: Entity, damage: DamageRoll, velocity: Vec3, radius: f32) -> Self {
+        Self {
+            projectile: Projectile {
+                owner,
+                damage,
+                life_time: 0.0,
+                max_life_time: 5.0,
+            },
+            rb: RigidBody::Dynamic,
+            collider: Collider::ball(radius),
+            sensor: Sensor,
+            velocity: Velocity::linear(velocity),
+            gravity: GravityScale(0.0),
+            ccd: Ccd::enabled(),
+            collision_events: ActiveEvents::COLLISION_EVENTS,
+        }
+    }
+}
+
+/// Spawns a projectile with its own visual child, so the collider is never affected by the mesh scale.
+pub(crate) fn spawn_projectile(
+    commands: &mut Commands,
+    resources: &GameResourceHandles,
+    owner: Entity,
+    damage: DamageRoll,
+    position: Vec3,
+    velocity: Vec3,
+    radius: f32,
+) -> Entity {
+    commands
+        .spawn(SpatialBundle {
+            transform: Transform::IDENTITY.with_translation(position),
+            ..default()
+        })
+        .insert(ProjectileBundle::new(owner, damage, velocity, radius))
+        .with_children(|parent| {
+            parent.spawn(PbrBundle {
+                mesh: resources.projectile_mesh.clone(),
+                material: resources.projectile_material.clone(),
+                transform: Transform::IDENTITY.with_scale(Vec3::splat(radius)),
+                ..default()
+            });
+        })
+        .id()
+}
+
+pub(crate) fn init(app: &mut App) {
+    app.add_event::<DamageEvent>();
+    app.add_event::<DeathEvent>();
+
+    // Projectiles only fly in real time, an encounter clears them out of the air
+    app.add_systems(
+        Update,
+        (
+            homing_system.run_if(exploring),
+            projectile_system.run_if(exploring),
+            clear_projectiles_listener,
+            damage_listener,
+        )
+            .chain(),
+    );
+    app.add_systems(PostUpdate, despawn_dead);
+}
+
+fn clear_projectiles_listener(
+    mut commands: Commands,
+    query: Query<Entity, With<Projectile>>,
+    mut started_events: EventReader<EncounterStartedEvent>,
+) {
+    if started_events.read().count() == 0 {
+        return;
+    }
+
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn homing_system(
+    mut query: Query<(&Transform, &Homing, &mut Velocity)>,
+    target_query: Query<&Transform, Without<Homing>>,
+    time: Res<Time>,
+) {
+    let dt = time.delta_seconds();
+
+    for (xform, homing, mut vel) in query.iter_mut() {
+        let Ok(target) = target_query.get(homing.target) else {
+            continue;
+        };
+
+        let speed = vel.linvel.length();
+        let current = vel.linvel.normalize_or_zero();
+        let wanted = (target.translation - xform.translation).normalize_or_zero();
+
+        if current == Vec3::ZERO || wanted == Vec3::ZERO {
+            continue;
+        }
+
+        let angle = current.angle_between(wanted);
+        let t = (homing.turn_rate * dt / angle.max(f32::EPSILON)).min(1.0);
+        vel.linvel = current.lerp(wanted, t).normalize_or_zero() * speed;
+    }
+}
+
+pub(crate) fn projectile_system(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Projectile)>,
+    mut collision_events: EventReader<CollisionEvent>,
+    mut damage_events: EventWriter<DamageEvent>,
+    health_query: Query<(), With<Health>>,
+    enemy_query: Query<(), With<Enemy>>,
+    time: Res<Time>,
+    mut rng: ResMut<GameRng>,
+) {
+    let dt = time.delta_seconds();
+    // A projectile can touch two things in one frame, it only hits the first
+    let mut spent: Vec<Entity> = Vec::new();
+
+    for ev in collision_events.read() {
+        let CollisionEvent::Started(a, b, _) = ev else {
+            continue;
+        };
+
+        for (projectile_entity, other) in [(*a, *b), (*b, *a)] {
+            let Ok((_, projectile)) = query.get(projectile_entity) else {
+                continue;
+            };
+            if spent.contains(&projectile_entity) {
+                continue;
+            }
+
+            // Ignore the shooter and other projectiles
+            if other == projectile.owner || query.contains(other) {
+                continue;
+            }
+
+            // Enemies don't shoot each other, their shots fly on towards the player
+            if enemy_query.contains(projectile.owner) && enemy_query.contains(other) {
+                continue;
+            }
+
+            if health_query.contains(other) {
+                damage_events.send(DamageEvent {
+                    target: other,
+                    amount: projectile.damage.roll(rng.stream(RngStream::Combat)),
+                    source: Some(projectile.owner),
+                });
+            }
+
+            commands.entity(projectile_entity).despawn_recursive();
+            spent.push(projectile_entity);
+        }
+    }
+
+    for (entity, mut projectile) in query.iter_mut() {
+        if spent.contains(&entity) {
+            continue;
+        }
+        projectile.life_time += dt;
+
+        if projectile.life_time >= projectile.max_life_time {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+pub(crate) fn damage_listener(
+    mut commands: Commands,
+    mut query: Query<(&mut Health, &Transform, Has<Enemy>)>,
+    mut damage_events: EventReader<DamageEvent>,
+    mut death_events: EventWriter<DeathEvent>,
+    mut add_message_event: EventWriter<AddUiMessageEvent>,
+) {
+    for ev in damage_events.read() {
+        let Ok((mut health, xform, is_enemy)) = query.get_mut(ev.target) else {
+            continue;
+        };
+
+        // Already dead, waiting to be despawned
+        if health.is_dead() {
+            continue;
+        }
+
+        health.current -= ev.amount;
+
+        if health.is_dead() {
+            death_events.send(DeathEvent {
+                entity: ev.target,
+                position: xform.translation,
+            });
+
+            if is_enemy {
+                add_message_event.send(AddUiMessageEvent {
+                    message: String::from("Slain!"),
+                    duration: 2.0,
+                });
+                commands.entity(ev.target).insert(Dead);
+            }
+        }
+    }
+}
+
+fn despawn_dead(mut commands: Commands, query: Query<Entity, With<Dead>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{encounter::UNARMED, enemy::EnemyKind, weapon::WeaponKind};
+
+    #[test]
+    fn every_damage_roll_parses() {
+        let rolls = WeaponKind::ALL
+            .iter()
+            .map(|kind| kind.def().damage)
+            .chain(EnemyKind::ALL.iter().map(|kind| kind.def().attack.damage))
+            .chain([UNARMED]);
+
+        for roll in rolls {
+            if let Err(e) = roll.parse() {
+                panic!("{}", e);
+            }
+        }
+    }
+}
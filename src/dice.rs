@@ -0,0 +1,943 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use rand::Rng;
+
+pub mod checks;
+pub mod effects;
+pub mod faces;
+pub mod history;
+pub mod items;
+pub mod notation;
+pub mod shapes;
+pub mod simulate;
+
+use crate::{
+    camera::CameraState,
+    encounter::exploring,
+    player::components::{Dice, DiceBundle, Eye, Inventory, MAX_DICE_CARRIED},
+    resources::load_resources,
+    rng::{GameRng, RngStream},
+    utils::ez_str,
+    AddUiMessageEvent, GameResourceHandles, MaterialName,
+};
+
+use self::{
+    items::{DiceItem, DiceMagic},
+    notation::{DiceExpr, RollResult, ThrownDice},
+    shapes::Polyhedron,
+};
+
+#[derive(Eq, PartialEq, Hash, Clone, Copy, Debug)]
+pub enum DiceKind {
+    D4,
+    D6,
+    D8,
+    D10,
+    D12,
+    D20,
+    /// Percentile die, printed 10 to 90 and 00 (which counts as 100).
+    D100,
+}
+
+impl DiceKind {
+    pub const ALL: [DiceKind; 7] = [
+        DiceKind::D4,
+        DiceKind::D6,
+        DiceKind::D8,
+        DiceKind::D10,
+        DiceKind::D12,
+        DiceKind::D20,
+        DiceKind::D100,
+    ];
+
+    pub fn from_sides(sides: u32) -> Option<Self> {
+        DiceKind::ALL.into_iter().find(|kind| kind.sides() == sides)
+    }
+
+    pub fn sides(&self) -> u32 {
+        match self {
+            DiceKind::D4 => 4,
+            DiceKind::D6 => 6,
+            DiceKind::D8 => 8,
+            DiceKind::D10 => 10,
+            DiceKind::D12 => 12,
+            DiceKind::D20 => 20,
+            DiceKind::D100 => 100,
+        }
+    }
+
+    pub fn name(&self) -> String {
+        format!("d{}", self.sides())
+    }
+
+    /// The next die in [`DiceKind::ALL`], wrapping around.
+    pub fn next(&self) -> Self {
+        let index = DiceKind::ALL.iter().position(|k| k == self).unwrap();
+        DiceKind::ALL[(index + 1) % DiceKind::ALL.len()]
+    }
+
+    /// Every face value, lowest first. A d100 is printed in tens.
+    pub fn values(&self) -> Vec<i32> {
+        match self {
+            DiceKind::D100 => (1..=10).map(|v| v * 10).collect(),
+            _ => (1..=self.sides() as i32).collect(),
+        }
+    }
+
+    /// How a face value is printed on the die.
+    pub fn label(&self, value: i32) -> String {
+        match (self, value) {
+            (DiceKind::D10, 10) => String::from("0"),
+            (DiceKind::D100, 100) => String::from("00"),
+            _ => value.to_string(),
+        }
+    }
+
+    fn radius(&self) -> f32 {
+        match self {
+            DiceKind::D4 => 0.42,
+            DiceKind::D6 => 0.25 * 3f32.sqrt(),
+            DiceKind::D8 => 0.36,
+            DiceKind::D10 | DiceKind::D100 => 0.36,
+            DiceKind::D12 => 0.36,
+            DiceKind::D20 => 0.38,
+        }
+    }
+
+    fn color(&self) -> Color {
+        match self {
+            DiceKind::D4 => Color::srgb(0.55, 0.85, 0.55),
+            DiceKind::D6 => Color::WHITE,
+            DiceKind::D8 => Color::srgb(0.55, 0.7, 0.95),
+            DiceKind::D10 => Color::srgb(0.95, 0.75, 0.4),
+            DiceKind::D12 => Color::srgb(0.8, 0.55, 0.9),
+            DiceKind::D20 => Color::srgb(0.95, 0.45, 0.4),
+            DiceKind::D100 => Color::srgb(0.85, 0.85, 0.85),
+        }
+    }
+}
+
+pub struct DiceFace {
+    /// Local direction that points up when this face is the result.
+    pub normal: Vec3,
+    pub value: i32,
+}
+
+pub struct DiceShape {
+    pub mesh: Handle<Mesh>,
+    pub material: Handle<StandardMaterial>,
+    pub collider: Collider,
+    pub faces: Vec<DiceFace>,
+    /// Sidecar to read `faces` from once the mesh has loaded.
+    pub faces_from: Option<String>,
+}
+
+impl DiceShape {
+    /// The face whose normal lines up best with world up, and how well it lines up (1.0 is flat).
+    /// `None` until the faces are known.
+    pub fn face_up(&self, rotation: Quat) -> Option<(&DiceFace, f32)> {
+        self.faces
+            .iter()
+            .map(|face| (face, (rotation * face.normal).dot(Vec3::Y)))
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+    }
+
+    pub fn value(&self, rotation: Quat) -> i32 {
+        self.face_up(rotation).map_or(0, |(face, _)| face.value)
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct DiceSet {
+    pub shapes: HashMap<DiceKind, DiceShape>,
+}
+
+impl DiceSet {
+    /// Uses .unwrap(), every kind is built at startup.
+    pub fn get(&self, kind: DiceKind) -> &DiceShape {
+        self.shapes.get(&kind).unwrap()
+    }
+}
+
+/// What to do with a die that comes to rest without a face flat on the ground.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CockedPolicy {
+    /// Pick it up and throw it again.
+    Reroll,
+    /// Knock it with a small impulse so it tips onto a face.
+    Nudge,
+    /// Count whichever face is closest to up.
+    NearestFace,
+}
+
+/// How thrown dice decide they've settled and what counts as a clean result.
+#[derive(Resource)]
+pub struct DiceSettings {
+    pub cocked_policy: CockedPolicy,
+    /// A die resting with its best face lined up worse than this is cocked,
+    /// see [`DiceShape::face_up`].
+    pub cocked_alignment: f32,
+    /// Both velocities have to stay under these for `settle_frames` fixed steps in a row.
+    pub settle_speed: f32,
+    pub settle_spin: f32,
+    pub settle_frames: u32,
+    /// Rerolls or nudges before a cocked die is read as it lies.
+    pub max_retries: u32,
+    /// Seconds before a die that never stops jittering is read as it lies.
+    pub settle_timeout: f32,
+}
+
+impl Default for DiceSettings {
+    fn default() -> Self {
+        Self {
+            cocked_policy: CockedPolicy::Nudge,
+            cocked_alignment: 0.97,
+            settle_speed: 0.05,
+            settle_spin: 0.1,
+            settle_frames: 10,
+            max_retries: 3,
+            settle_timeout: 10.0,
+        }
+    }
+}
+
+/// Why a roll was made, so listeners can pick out the rolls they care about.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RollPurpose {
+    /// Thrown for the fun of it.
+    Casual,
+    Stat,
+    /// The d20 of a pending [`checks::SkillCheck`].
+    Check(Entity),
+    /// To-hit dice in a turn-based encounter.
+    Attack,
+    Damage,
+    /// Thrown into a gambler's tray, by the player or the house.
+    Gamble {
+        house: bool,
+    },
+}
+
+impl RollPurpose {
+    /// Only dice thrown freely explode, curse or heal. Checks, fights and gambling have
+    /// their own stakes.
+    pub fn has_face_effects(&self) -> bool {
+        matches!(self, RollPurpose::Casual | RollPurpose::Stat)
+    }
+}
+
+/// Rolls a dice expression, either straight from the RNG or by throwing physical dice.
+#[derive(Event)]
+pub struct RollExpressionEvent {
+    pub roller: Entity,
+    pub expression: DiceExpr,
+    pub purpose: RollPurpose,
+    pub physical: bool,
+    /// Kept dice showing this or more count as successes.
+    pub target: Option<i32>,
+    /// Frame the settled dice with the camera.
+    pub show: bool,
+    /// `None` tosses the dice gently ahead of the roller.
+    pub throw: Option<DiceThrow>,
+    /// Thrown as the first die of the roll in place of a plain one of its kind.
+    pub item: Option<DiceItem>,
+}
+
+/// An aimed throw, every die leaves `origin` with this velocity and spin.
+#[derive(Clone, Copy, Debug)]
+pub struct DiceThrow {
+    pub origin: Vec3,
+    pub velocity: Vec3,
+    pub spin: Vec3,
+}
+
+/// Sent once for a whole roll, after every die in it has settled.
+#[derive(Event)]
+pub struct ExpressionRolledEvent {
+    pub roller: Entity,
+    /// `None` for rolls made without physical dice.
+    pub group: Option<Entity>,
+    pub expression: DiceExpr,
+    pub purpose: RollPurpose,
+    /// Total plus each die's face.
+    pub result: RollResult,
+    pub successes: Option<u32>,
+    /// Middle of the settled dice, or the roller for RNG rolls.
+    pub position: Vec3,
+}
+
+/// Sent for every physical die in a roll once the whole group has settled,
+/// just before the group's [`ExpressionRolledEvent`].
+#[derive(Event, Clone)]
+pub struct DiceRollEvent {
+    pub roller: Entity,
+    pub die: Entity,
+    pub kind: DiceKind,
+    pub value: i32,
+    pub group: Entity,
+    pub purpose: RollPurpose,
+    pub position: Vec3,
+    /// Came to rest leaning on something rather than flat on a face.
+    pub cocked: bool,
+    /// Its face was thrown away by a reroll and doesn't count.
+    pub rerolled: bool,
+    pub item: Option<DiceItem>,
+}
+
+/// Dice thrown together as one roll, waiting for all of them to settle.
+#[derive(Component)]
+pub struct RollGroup {
+    pub roller: Entity,
+    pub expression: DiceExpr,
+    pub purpose: RollPurpose,
+    pub target: Option<i32>,
+    pub show: bool,
+    /// Dice thrown for this roll, in throw order.
+    pub dice: Vec<Entity>,
+    /// Where follow-up dice for rerolls and explosions are thrown from.
+    pub origin: Vec3,
+    pub direction: Vec3,
+}
+
+/// Tags a die as part of a [`RollGroup`].
+#[derive(Component)]
+pub struct RollPart(pub Entity);
+
+/// A settled die left lying around after its roll. The owner gets it back by walking
+/// over it, or it finds its own way home after a while.
+#[derive(Component)]
+pub struct LooseDie {
+    /// `None` for extra dice thrown by rerolls and explosions, they just vanish.
+    pub owner: Option<Entity>,
+    pub returns_in: f32,
+}
+
+/// The light [`frame_dice`] puts over a roll, removed once the camera scene ends.
+#[derive(Component)]
+pub struct DiceSpotlight;
+
+const RETRIEVE_DISTANCE: f32 = 1.5;
+const DICE_RETURN_TIME: f32 = 30.0;
+
+/// How far ahead of the eye aimed dice leave the hand.
+pub const THROW_REACH: f32 = 0.8;
+/// Gap between dice thrown side by side.
+const THROW_SPACING: f32 = 0.35;
+/// Length of each step when following a throw's arc, and how many to take.
+const ARC_STEP: f32 = 1.0 / 30.0;
+const ARC_STEPS: usize = 90;
+/// Which atlas tile of the d6 model shows which value.
+const D6_FACES_PATH: &str = "assets/meshes/dice.faces";
+
+pub(crate) fn init(app: &mut App) {
+    app.insert_resource(DiceSet::default());
+    app.insert_resource(DiceSettings::default());
+    app.insert_resource(history::RollHistory::default());
+    app.add_event::<RollExpressionEvent>();
+    app.add_event::<ExpressionRolledEvent>();
+    app.add_event::<DiceRollEvent>();
+    app.add_event::<checks::SkillCheckEvent>();
+    app.add_event::<checks::CheckCriticalSuccessEvent>();
+    app.add_event::<checks::CheckSuccessEvent>();
+    app.add_event::<checks::CheckFailureEvent>();
+    app.add_event::<checks::CheckFumbleEvent>();
+
+    app.add_systems(PreStartup, build_dice_set.after(load_resources));
+    // Settling counts physics steps, not rendered frames, so a seed lands the same dice
+    // whatever the frame rate.
+    app.add_systems(
+        FixedUpdate,
+        (
+            effects::dice_impact_system.run_if(exploring),
+            settle_dice_system,
+        )
+            .chain()
+            .after(PhysicsSet::Writeback),
+    );
+    app.add_systems(Startup, effects::load_dice_effects);
+    app.add_systems(
+        Update,
+        (
+            read_mesh_faces,
+            roll_expression_listener,
+            roll_group_system.after(roll_expression_listener),
+            effects::face_effect_listener.after(roll_group_system),
+            loose_dice_system,
+            dice_spotlight_cleanup,
+            history::record_rolls_listener
+                .after(roll_expression_listener)
+                .after(roll_group_system),
+            history::roll_history_panel_system,
+            checks::skill_check_listener.before(roll_expression_listener),
+            checks::resolve_checks_listener
+                .after(roll_expression_listener)
+                .after(roll_group_system),
+        ),
+    );
+}
+
+/// Spawns a die at `position` with a random orientation. It reports its face through
+/// [`Dice`] once it settles.
+pub fn throw_die(
+    commands: &mut Commands,
+    dice_set: &DiceSet,
+    kind: DiceKind,
+    position: Vec3,
+    impulse: ExternalImpulse,
+    rng: &mut impl Rng,
+) -> Entity {
+    let shape = dice_set.get(kind);
+
+    commands
+        .spawn(PbrBundle {
+            mesh: shape.mesh.clone(),
+            material: shape.material.clone(),
+            transform: Transform::IDENTITY
+                .with_translation(position)
+                .with_rotation(crate::mathx::random::quat(rng)),
+            ..default()
+        })
+        .insert(DiceBundle::new(kind, shape.collider.clone()))
+        .insert(impulse)
+        .id()
+}
+
+/// A gentle forward toss with some random spin.
+fn toss(direction: Vec3, rng: &mut impl Rng) -> ExternalImpulse {
+    ExternalImpulse {
+        impulse: direction * rng.gen_range(0.4..0.8) + Vec3::Y * 0.2,
+        torque_impulse: crate::mathx::random::vec3(rng) * rng.gen_range(0.1..0.3),
+    }
+}
+
+/// Follows a throw through the air until it hits something. Returns the arc and, if it
+/// lands within a few seconds, the landing point and surface normal.
+pub fn throw_arc(
+    rapier_context: &RapierContext,
+    throw: &DiceThrow,
+    gravity: Vec3,
+    filter: QueryFilter,
+) -> (Vec<Vec3>, Option<(Vec3, Vec3)>) {
+    let mut points = vec![throw.origin];
+    let mut position = throw.origin;
+    let mut velocity = throw.velocity;
+
+    for _ in 0..ARC_STEPS {
+        let next = position + velocity * ARC_STEP + gravity * ARC_STEP * ARC_STEP * 0.5;
+        velocity += gravity * ARC_STEP;
+
+        if let Some((_, hit)) =
+            rapier_context.cast_ray_and_get_normal(position, next - position, 1.0, true, filter)
+        {
+            points.push(hit.point);
+            return (points, Some((hit.point, hit.normal)));
+        }
+
+        points.push(next);
+        position = next;
+    }
+
+    (points, None)
+}
+
+/// A light knock to tip a cocked die over.
+fn nudge(rng: &mut impl Rng) -> ExternalImpulse {
+    let sideways = crate::mathx::random::vec2(rng) * 0.05;
+
+    ExternalImpulse {
+        impulse: Vec3::new(sideways.x, rng.gen_range(0.1..0.2), sideways.y),
+        torque_impulse: crate::mathx::random::vec3(rng) * 0.1,
+    }
+}
+
+pub(crate) fn roll_expression_listener(
+    mut commands: Commands,
+    roller_query: Query<(&Transform, Option<&Eye>)>,
+    dice_set: Res<DiceSet>,
+    mut rng: ResMut<GameRng>,
+    mut events: EventReader<RollExpressionEvent>,
+    mut rolled_events: EventWriter<ExpressionRolledEvent>,
+) {
+    let rng = rng.stream(RngStream::Dice);
+
+    for ev in events.read() {
+        let Ok((xform, eye)) = roller_query.get(ev.roller) else {
+            continue;
+        };
+
+        let kinds = ev.expression.dice();
+
+        if !ev.physical || kinds.is_empty() {
+            let result = ev.expression.roll(rng);
+
+            rolled_events.send(ExpressionRolledEvent {
+                roller: ev.roller,
+                group: None,
+                expression: ev.expression.clone(),
+                purpose: ev.purpose.clone(),
+                successes: ev.target.map(|target| result.successes(target)),
+                result,
+                position: xform.translation,
+            });
+            continue;
+        }
+
+        let (origin, forward) = match eye {
+            Some(eye) => (eye.position, *eye.forward()),
+            None => (xform.translation, *xform.forward()),
+        };
+        let forward = match ev.throw {
+            Some(throw) => throw.velocity,
+            None => forward,
+        };
+        let direction = Vec3::new(forward.x, 0.0, forward.z).normalize_or_zero();
+        let right = direction.cross(Vec3::Y);
+        let centre = match ev.throw {
+            Some(throw) => throw.origin,
+            None => origin + direction * 1.5,
+        };
+
+        let group = commands.spawn_empty().id();
+        let dice = kinds
+            .iter()
+            .enumerate()
+            .map(|(i, kind)| {
+                let offset = (i as f32 - (kinds.len() - 1) as f32 / 2.0) * THROW_SPACING;
+                let impulse = match ev.throw {
+                    Some(_) => ExternalImpulse::default(),
+                    None => toss(direction, rng),
+                };
+                let die = throw_die(
+                    &mut commands,
+                    &dice_set,
+                    *kind,
+                    centre + right * offset,
+                    impulse,
+                    rng,
+                );
+                commands.entity(die).insert(RollPart(group));
+
+                if let Some(item) = ev.item.filter(|item| i == 0 && item.def().kind == *kind) {
+                    items::make_item_die(&mut commands, &dice_set, die, item);
+                }
+
+                if let Some(throw) = ev.throw {
+                    commands.entity(die).insert(Velocity {
+                        linvel: throw.velocity,
+                        angvel: throw.spin,
+                    });
+                }
+                die
+            })
+            .collect();
+
+        commands.entity(group).insert(RollGroup {
+            roller: ev.roller,
+            expression: ev.expression.clone(),
+            purpose: ev.purpose.clone(),
+            target: ev.target,
+            show: ev.show,
+            dice,
+            origin: centre,
+            direction,
+        });
+    }
+}
+
+/// Reads the face of each die once it has come to rest. A die is at rest when both its
+/// velocities stay low for a few frames in a row, or when Rapier puts it to sleep.
+/// Cocked dice are handled according to [`DiceSettings::cocked_policy`].
+fn settle_dice_system(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Transform, &mut Velocity, &Sleeping, &mut Dice)>,
+    dice_set: Res<DiceSet>,
+    settings: Res<DiceSettings>,
+    time: Res<Time>,
+    mut rng: ResMut<GameRng>,
+) {
+    let rng = rng.stream(RngStream::Dice);
+
+    for (entity, mut xform, mut vel, sleeping, mut dice) in query.iter_mut() {
+        if dice.rolled {
+            continue;
+        }
+
+        dice.since_thrown += time.delta_seconds();
+        dice.speed = vel.linvel.length();
+
+        let still = vel.linvel.length() <= settings.settle_speed
+            && vel.angvel.length() <= settings.settle_spin;
+        dice.still_frames = if still { dice.still_frames + 1 } else { 0 };
+
+        let timed_out = dice.since_thrown >= settings.settle_timeout;
+        if !sleeping.sleeping && dice.still_frames < settings.settle_frames && !timed_out {
+            continue;
+        }
+
+        // The d6 reads its faces off its mesh, which may not have loaded yet.
+        let Some((face, alignment)) = dice_set.get(dice.kind).face_up(xform.rotation) else {
+            continue;
+        };
+
+        let magic = dice.item.and_then(|item| item.def().magic);
+        let cocked = alignment < settings.cocked_alignment;
+        let lucky = magic == Some(DiceMagic::RerollOnes) && face.value == 1;
+
+        let retry = match (lucky, cocked) {
+            _ if timed_out || dice.retries >= settings.max_retries => None,
+            (true, _) => Some(CockedPolicy::Reroll),
+            (false, true) => Some(settings.cocked_policy),
+            (false, false) => None,
+        };
+
+        if retry.is_some() && retry != Some(CockedPolicy::NearestFace) {
+            if retry == Some(CockedPolicy::Reroll) {
+                let direction = crate::mathx::random::vec2(rng).normalize_or_zero();
+                xform.translation += Vec3::Y * 0.3;
+                xform.rotation = crate::mathx::random::quat(rng);
+                *vel = Velocity::zero();
+                commands
+                    .entity(entity)
+                    .insert(toss(Vec3::new(direction.x, 0.0, direction.y), rng));
+            } else {
+                commands.entity(entity).insert(nudge(rng));
+            }
+
+            dice.retries += 1;
+            dice.still_frames = 0;
+            dice.since_thrown = 0.0;
+            continue;
+        }
+
+        dice.rolled = true;
+        dice.value = face.value;
+        dice.cocked = cocked;
+
+        // Magic turns the chosen face up however it landed.
+        if let Some(DiceMagic::FixedFace(value)) = magic {
+            let shape = dice_set.get(dice.kind);
+            if let Some(fixed) = shape.faces.iter().find(|face| face.value == value) {
+                let up = xform.rotation * fixed.normal;
+                xform.rotation = Quat::from_rotation_arc(up.normalize(), Vec3::Y) * xform.rotation;
+                dice.value = value;
+                dice.cocked = false;
+            }
+        }
+    }
+}
+
+/// Waits for every die in a group to settle, then evaluates the roll. Rerolls and
+/// explosions that need more dice throw them one at a time.
+fn roll_group_system(
+    mut commands: Commands,
+    mut group_query: Query<(Entity, &mut RollGroup)>,
+    dice_query: Query<(&Dice, &Transform)>,
+    dice_set: Res<DiceSet>,
+    mut rng: ResMut<GameRng>,
+    mut camera_state: ResMut<CameraState>,
+    mut rolled_events: EventWriter<ExpressionRolledEvent>,
+    mut dice_roll_events: EventWriter<DiceRollEvent>,
+    mut add_message_event: EventWriter<AddUiMessageEvent>,
+) {
+    for (entity, mut group) in group_query.iter_mut() {
+        let settled: Option<Vec<(&Dice, Vec3)>> = group
+            .dice
+            .iter()
+            .map(|die| {
+                dice_query
+                    .get(*die)
+                    .ok()
+                    .filter(|(dice, _)| dice.rolled)
+                    .map(|(dice, xform)| (dice, xform.translation))
+            })
+            .collect();
+
+        let Some(settled) = settled else {
+            continue;
+        };
+
+        let thrown: Vec<(DiceKind, i32)> = settled
+            .iter()
+            .map(|(dice, _)| (dice.kind, dice.value))
+            .collect();
+        let rng = rng.stream(RngStream::Dice);
+        let mut source = ThrownDice::new(&thrown, rng);
+
+        let Some(result) = group.expression.evaluate(&mut source) else {
+            if let Some(kind) = source.missing {
+                let (origin, direction) = (group.origin, group.direction);
+                let impulse = toss(direction, rng);
+                let die = throw_die(&mut commands, &dice_set, kind, origin, impulse, rng);
+                commands.entity(die).insert(RollPart(entity));
+                group.dice.push(die);
+            }
+            continue;
+        };
+
+        let centre = settled.iter().map(|(_, pos)| *pos).sum::<Vec3>() / settled.len() as f32;
+        let spread = settled
+            .iter()
+            .map(|(_, pos)| pos.distance(centre))
+            .fold(0.0, f32::max);
+        let successes = group.target.map(|target| result.successes(target));
+
+        // Rolls thrown without the close-up, like a gambler's hidden dice, stay quiet too
+        if group.show {
+            let mut message = format!("{}: {}", group.expression, result.describe());
+            if let Some(successes) = successes {
+                message += &format!(", {} successes", successes);
+            }
+            add_message_event.send(AddUiMessageEvent {
+                message,
+                duration: 4.0,
+            });
+
+            frame_dice(&mut commands, &mut camera_state, centre, spread);
+        }
+
+        for (i, (dice, position)) in settled.iter().enumerate() {
+            dice_roll_events.send(DiceRollEvent {
+                roller: group.roller,
+                die: group.dice[i],
+                kind: dice.kind,
+                value: dice.value,
+                group: entity,
+                purpose: group.purpose.clone(),
+                position: *position,
+                cocked: dice.cocked,
+                rerolled: source.discarded.contains(&i),
+                item: dice.item,
+            });
+        }
+
+        // Only the dice the roller threw go back to them.
+        let carried = group.expression.dice().len();
+        for (i, die) in group.dice.iter().enumerate() {
+            commands.entity(*die).insert(LooseDie {
+                owner: (i < carried).then_some(group.roller),
+                returns_in: DICE_RETURN_TIME,
+            });
+        }
+
+        rolled_events.send(ExpressionRolledEvent {
+            roller: group.roller,
+            group: Some(entity),
+            expression: group.expression.clone(),
+            purpose: group.purpose.clone(),
+            result,
+            successes,
+            position: centre,
+        });
+        commands.entity(entity).despawn();
+    }
+}
+
+/// Pulls the camera back far enough to see dice spread `spread` around `centre`,
+/// lights them and plays the close-up sequence around them.
+fn frame_dice(commands: &mut Commands, camera_state: &mut CameraState, centre: Vec3, spread: f32) {
+    let distance = 1.0 + spread / 0.5;
+
+    let sl_pitch = crate::mathx::f32::degrees_to_radians(-90.0);
+    commands
+        .spawn(SpotLightBundle {
+            spot_light: SpotLight {
+                color: Srgba::hex("#e6bfaa").unwrap().into(),
+                intensity: 150_000.0 * distance,
+                shadows_enabled: true,
+                ..default()
+            },
+            transform: Transform::IDENTITY
+                .with_translation(centre + Vec3::Y * (1.4 + spread))
+                .with_rotation(Quat::from_euler(EulerRot::XYZ, sl_pitch, 0.0, 0.0)),
+            ..default()
+        })
+        .insert(DiceSpotlight);
+
+    camera_state.play("dice_closeup", centre, distance);
+}
+
+fn loose_dice_system(
+    mut commands: Commands,
+    mut query: Query<(Entity, &Transform, &Dice, &mut LooseDie)>,
+    mut owner_query: Query<(&Transform, &mut Inventory), Without<Dice>>,
+    time: Res<Time>,
+    mut add_message_event: EventWriter<AddUiMessageEvent>,
+) {
+    let dt = time.delta_seconds();
+
+    for (entity, xform, dice, mut loose) in query.iter_mut() {
+        loose.returns_in -= dt;
+
+        let owner = loose
+            .owner
+            .and_then(|owner| owner_query.get_mut(owner).ok());
+
+        let Some((owner_xform, mut inventory)) = owner else {
+            if loose.returns_in <= 0.0 {
+                commands.entity(entity).despawn();
+            }
+            continue;
+        };
+
+        let picked_up = owner_xform.translation.distance(xform.translation) <= RETRIEVE_DISTANCE;
+        if !picked_up && loose.returns_in > 0.0 {
+            continue;
+        }
+
+        match dice.item {
+            Some(item) => inventory.dice_items.push(item),
+            None => inventory.dice = (inventory.dice + 1).min(MAX_DICE_CARRIED),
+        }
+        commands.entity(entity).despawn();
+
+        let name = dice
+            .item
+            .map_or(dice.kind.name(), |item| String::from(item.def().name));
+        let message = if picked_up {
+            format!("Picked up your {}", name)
+        } else {
+            format!("Your {} rolls back to you", name)
+        };
+        add_message_event.send(AddUiMessageEvent {
+            message,
+            duration: 1.5,
+        });
+    }
+}
+
+fn dice_spotlight_cleanup(
+    mut commands: Commands,
+    query: Query<Entity, With<DiceSpotlight>>,
+    camera_state: Res<CameraState>,
+) {
+    if camera_state.scene_params.is_some() {
+        return;
+    }
+
+    for entity in query.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+fn build_dice_set(
+    mut dice_set: ResMut<DiceSet>,
+    assets: Res<AssetServer>,
+    resources: Res<GameResourceHandles>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    let numbers: Handle<Image> = assets.load(ez_str("dice/numbers.png"));
+
+    for kind in DiceKind::ALL {
+        let shape = match kind {
+            // The d6 keeps its textured model, its faces are read off the card atlas.
+            DiceKind::D6 => {
+                let hull = polyhedron(kind).scaled(kind.radius());
+
+                DiceShape {
+                    mesh: resources.dice_mesh.clone(),
+                    material: resources.get_material(MaterialName::Dice),
+                    collider: Collider::convex_hull(&hull.vertices).unwrap(),
+                    faces: Vec::new(),
+                    faces_from: Some(ez_str(D6_FACES_PATH)),
+                }
+            }
+            _ => {
+                let poly = polyhedron(kind).scaled(kind.radius());
+                let faces = polyhedron_faces(kind, &poly);
+
+                let cells: Vec<usize> = faces
+                    .iter()
+                    .map(|face| shapes::atlas_cell(&kind.label(face.value)))
+                    .collect();
+
+                if let Err(e) = faces::validate_faces(kind, &faces) {
+                    panic!("Generated a broken die: {}", e);
+                }
+
+                DiceShape {
+                    mesh: meshes.add(shapes::mesh(&poly, &cells)),
+                    material: assets.add(StandardMaterial {
+                        base_color: kind.color(),
+                        base_color_texture: Some(numbers.clone()),
+                        perceptual_roughness: 0.5,
+                        metallic: 0.01,
+                        ..default()
+                    }),
+                    collider: Collider::convex_hull(&poly.vertices).unwrap(),
+                    faces,
+                    faces_from: None,
+                }
+            }
+        };
+
+        dice_set.shapes.insert(kind, shape);
+    }
+}
+
+/// Fills in the faces of dice whose values come from their mesh, once it has loaded.
+/// A mismatch between the mesh, its UVs and the sidecar is a broken asset, so this panics.
+fn read_mesh_faces(mut dice_set: ResMut<DiceSet>, meshes: Res<Assets<Mesh>>) {
+    for (kind, shape) in dice_set.shapes.iter_mut() {
+        let Some(path) = &shape.faces_from else {
+            continue;
+        };
+        let Some(mesh) = meshes.get(&shape.mesh) else {
+            continue;
+        };
+
+        let result = faces::FaceSidecar::load(path)
+            .and_then(|sidecar| faces::faces_from_mesh(mesh, &sidecar))
+            .and_then(|faces| faces::validate_faces(*kind, &faces).map(|_| faces));
+
+        match result {
+            Ok(faces) => {
+                shape.faces = faces;
+                shape.faces_from = None;
+            }
+            Err(e) => panic!("Can't read the {} faces: {}", kind.name(), e),
+        }
+    }
+}
+
+fn polyhedron(kind: DiceKind) -> Polyhedron {
+    match kind {
+        DiceKind::D4 => shapes::tetrahedron(),
+        DiceKind::D6 => shapes::cube(),
+        DiceKind::D8 => shapes::octahedron(),
+        DiceKind::D10 | DiceKind::D100 => shapes::trapezohedron(),
+        DiceKind::D12 => shapes::dodecahedron(),
+        DiceKind::D20 => shapes::icosahedron(),
+    }
+}
+
+/// Faces of a generated die, in the same order as the polyhedron's.
+fn polyhedron_faces(kind: DiceKind, poly: &Polyhedron) -> Vec<DiceFace> {
+    let normals = poly.face_normals();
+    let values = face_values(kind, &normals);
+
+    // A d4 is read from the face it lands on.
+    normals
+        .iter()
+        .zip(values)
+        .map(|(normal, value)| DiceFace {
+            normal: if kind == DiceKind::D4 {
+                -*normal
+            } else {
+                *normal
+            },
+            value,
+        })
+        .collect()
+}
+
+fn face_values(kind: DiceKind, normals: &[Vec3]) -> Vec<i32> {
+    match kind {
+        DiceKind::D100 => shapes::opposite_values(normals, 10)
+            .into_iter()
+            .map(|v| v * 10)
+            .collect(),
+        _ => shapes::opposite_values(normals, kind.sides() as i32),
+    }
+}
@@ -0,0 +1,312 @@
+use bevy::prelude::*;
+
+use crate::{player::components::Inventory, AddUiMessageEvent};
+
+use super::{notation::DiceExpr, ExpressionRolledEvent, RollExpressionEvent, RollPurpose};
+
+#[derive(Eq, PartialEq, Hash, Clone, Copy, Debug)]
+pub enum Ability {
+    Strength,
+    Dexterity,
+    Constitution,
+    Intelligence,
+    Wisdom,
+    Charisma,
+}
+
+impl Ability {
+    pub const ALL: [Ability; 6] = [
+        Ability::Strength,
+        Ability::Dexterity,
+        Ability::Constitution,
+        Ability::Intelligence,
+        Ability::Wisdom,
+        Ability::Charisma,
+    ];
+
+    pub fn short_name(&self) -> &'static str {
+        match self {
+            Ability::Strength => "STR",
+            Ability::Dexterity => "DEX",
+            Ability::Constitution => "CON",
+            Ability::Intelligence => "INT",
+            Ability::Wisdom => "WIS",
+            Ability::Charisma => "CHA",
+        }
+    }
+}
+
+/// Ability scores, 10 is average.
+#[derive(Component, Clone, Debug)]
+pub struct Abilities {
+    pub scores: [i32; 6],
+}
+
+impl Default for Abilities {
+    fn default() -> Self {
+        Self { scores: [10; 6] }
+    }
+}
+
+impl Abilities {
+    pub fn score(&self, ability: Ability) -> i32 {
+        self.scores[ability as usize]
+    }
+
+    /// Added to checks, +1 for every 2 points above 10.
+    pub fn modifier(&self, ability: Ability) -> i32 {
+        (self.score(ability) - 10).div_euclid(2)
+    }
+}
+
+#[derive(Eq, PartialEq, Hash, Clone, Copy, Debug)]
+pub enum Skill {
+    Lockpicking,
+    DisarmTrap,
+    Persuasion,
+}
+
+impl Skill {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Skill::Lockpicking => "Lockpicking",
+            Skill::DisarmTrap => "Disarm trap",
+            Skill::Persuasion => "Persuasion",
+        }
+    }
+
+    pub fn ability(&self) -> Ability {
+        match self {
+            Skill::Lockpicking => Ability::Dexterity,
+            Skill::DisarmTrap => Ability::Dexterity,
+            Skill::Persuasion => Ability::Charisma,
+        }
+    }
+}
+
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum CheckOutcome {
+    /// A natural 20, whatever the DC.
+    CriticalSuccess,
+    Success,
+    Failure,
+    /// A natural 1, whatever the total.
+    Fumble,
+}
+
+impl CheckOutcome {
+    /// A natural 20 or 1 decides the check on its own, otherwise the `total` has to reach
+    /// the `dc`.
+    pub fn from_roll(natural: i32, total: i32, dc: i32) -> CheckOutcome {
+        match natural {
+            20 => CheckOutcome::CriticalSuccess,
+            1 => CheckOutcome::Fumble,
+            _ if total >= dc => CheckOutcome::Success,
+            _ => CheckOutcome::Failure,
+        }
+    }
+
+    pub fn succeeded(&self) -> bool {
+        matches!(self, CheckOutcome::CriticalSuccess | CheckOutcome::Success)
+    }
+}
+
+/// Asks `roller` to roll d20 + the skill's ability modifier against `dc`.
+#[derive(Event)]
+pub struct SkillCheckEvent {
+    pub roller: Entity,
+    pub skill: Skill,
+    pub dc: i32,
+    /// Whatever is being picked, disarmed or persuaded.
+    pub target: Option<Entity>,
+}
+
+/// A check waiting for its die to settle.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct SkillCheck {
+    pub roller: Entity,
+    pub skill: Skill,
+    pub dc: i32,
+    pub target: Option<Entity>,
+    pub modifier: i32,
+}
+
+/// How a finished check went, carried by each of the outcome events.
+#[derive(Clone, Copy, Debug)]
+pub struct CheckResult {
+    pub check: SkillCheck,
+    pub natural: i32,
+    pub total: i32,
+    pub outcome: CheckOutcome,
+}
+
+#[derive(Event)]
+pub struct CheckCriticalSuccessEvent(pub CheckResult);
+
+#[derive(Event)]
+pub struct CheckSuccessEvent(pub CheckResult);
+
+#[derive(Event)]
+pub struct CheckFailureEvent(pub CheckResult);
+
+#[derive(Event)]
+pub struct CheckFumbleEvent(pub CheckResult);
+
+/// Shows what the check needs and throws the d20. Rollers with no dice left, or no
+/// inventory at all, roll from the RNG instead.
+pub(crate) fn skill_check_listener(
+    mut commands: Commands,
+    mut roller_query: Query<(Option<&Abilities>, Option<&mut Inventory>)>,
+    mut events: EventReader<SkillCheckEvent>,
+    mut roll_events: EventWriter<RollExpressionEvent>,
+    mut add_message_event: EventWriter<AddUiMessageEvent>,
+) {
+    for ev in events.read() {
+        let Ok((abilities, inventory)) = roller_query.get_mut(ev.roller) else {
+            continue;
+        };
+
+        let ability = ev.skill.ability();
+        let modifier = abilities.map_or(0, |abilities| abilities.modifier(ability));
+
+        let physical = match inventory {
+            Some(mut inventory) if inventory.dice > 0 => {
+                inventory.dice -= 1;
+                true
+            }
+            _ => false,
+        };
+
+        let check = SkillCheck {
+            roller: ev.roller,
+            skill: ev.skill,
+            dc: ev.dc,
+            target: ev.target,
+            modifier,
+        };
+        let entity = commands.spawn(check).id();
+
+        add_message_event.send(AddUiMessageEvent {
+            message: format!(
+                "{}: d20 + {} ({:+}) vs DC {}",
+                ev.skill.name(),
+                ability.short_name(),
+                modifier,
+                ev.dc
+            ),
+            duration: 3.0,
+        });
+
+        roll_events.send(RollExpressionEvent {
+            roller: ev.roller,
+            expression: DiceExpr::parse(&format!("1d20{:+}", modifier)).unwrap(),
+            purpose: RollPurpose::Check(entity),
+            physical,
+            target: None,
+            show: physical,
+            throw: None,
+            item: None,
+        });
+    }
+}
+
+/// Turns the settled d20 into one of the four outcome events.
+pub(crate) fn resolve_checks_listener(
+    mut commands: Commands,
+    check_query: Query<&SkillCheck>,
+    mut events: EventReader<ExpressionRolledEvent>,
+    mut critical_events: EventWriter<CheckCriticalSuccessEvent>,
+    mut success_events: EventWriter<CheckSuccessEvent>,
+    mut failure_events: EventWriter<CheckFailureEvent>,
+    mut fumble_events: EventWriter<CheckFumbleEvent>,
+    mut add_message_event: EventWriter<AddUiMessageEvent>,
+) {
+    for ev in events.read() {
+        let RollPurpose::Check(entity) = ev.purpose else {
+            continue;
+        };
+        let Ok(check) = check_query.get(entity) else {
+            continue;
+        };
+        commands.entity(entity).despawn();
+
+        let natural = ev
+            .result
+            .dice
+            .iter()
+            .find(|die| die.kept && die.sides == 20)
+            .map_or(0, |die| die.value);
+        let total = ev.result.total;
+
+        let outcome = CheckOutcome::from_roll(natural, total, check.dc);
+
+        let result = CheckResult {
+            check: *check,
+            natural,
+            total,
+            outcome,
+        };
+
+        let verdict = match outcome {
+            CheckOutcome::CriticalSuccess => {
+                critical_events.send(CheckCriticalSuccessEvent(result));
+                "critical success!"
+            }
+            CheckOutcome::Success => {
+                success_events.send(CheckSuccessEvent(result));
+                "success"
+            }
+            CheckOutcome::Failure => {
+                failure_events.send(CheckFailureEvent(result));
+                "failure"
+            }
+            CheckOutcome::Fumble => {
+                fumble_events.send(CheckFumbleEvent(result));
+                "fumble!"
+            }
+        };
+
+        add_message_event.send(AddUiMessageEvent {
+            message: format!(
+                "{}: {} vs DC {}, {}",
+                check.skill.name(),
+                total,
+                check.dc,
+                verdict
+            ),
+            duration: 3.0,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn total_against_dc() {
+        assert_eq!(CheckOutcome::from_roll(12, 15, 15), CheckOutcome::Success);
+        assert_eq!(CheckOutcome::from_roll(12, 16, 15), CheckOutcome::Success);
+        assert_eq!(CheckOutcome::from_roll(12, 14, 15), CheckOutcome::Failure);
+    }
+
+    #[test]
+    fn natural_one_fumbles_above_the_dc() {
+        let outcome = CheckOutcome::from_roll(1, 18, 10);
+        assert_eq!(outcome, CheckOutcome::Fumble);
+        assert!(!outcome.succeeded());
+    }
+
+    #[test]
+    fn natural_twenty_succeeds_below_the_dc() {
+        let outcome = CheckOutcome::from_roll(20, 17, 25);
+        assert_eq!(outcome, CheckOutcome::CriticalSuccess);
+        assert!(outcome.succeeded());
+    }
+
+    #[test]
+    fn other_naturals_are_not_critical() {
+        assert_eq!(CheckOutcome::from_roll(19, 30, 10), CheckOutcome::Success);
+        assert_eq!(CheckOutcome::from_roll(2, -3, 10), CheckOutcome::Failure);
+    }
+}
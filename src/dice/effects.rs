@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use crate::{
+    camera::effects::CameraTraumaEvent,
+    combat::{DamageEvent, Health},
+    player::components::Dice,
+    utils::{data_lines, expect_data, load_data},
+    AddUiMessageEvent,
+};
+
+use super::{DiceKind, DiceRollEvent, RollGroup, RollPart};
+
+/// What happens where a die lands showing a particular face.
+#[derive(Clone, Debug, PartialEq)]
+pub enum FaceEffect {
+    /// Hurts everything but the thrower within `radius`.
+    Explosion {
+        radius: f32,
+        damage: i32,
+    },
+    /// Hurts the thrower.
+    Curse {
+        damage: i32,
+    },
+    Heal {
+        amount: i32,
+    },
+}
+
+/// Damage a die deals when it hits something faster than `min_speed`.
+#[derive(Clone, Copy, Debug)]
+pub struct ImpactDef {
+    pub min_speed: f32,
+    pub damage_per_speed: f32,
+}
+
+/// Impact damage and face effects for each kind of die, read from `dice/effects.txt`.
+#[derive(Resource, Default)]
+pub struct DiceEffects {
+    pub impacts: HashMap<DiceKind, ImpactDef>,
+    pub faces: HashMap<(DiceKind, i32), FaceEffect>,
+}
+
+impl DiceEffects {
+    pub fn load(path: &str) -> Result<Self, String> {
+        load_data(path, Self::parse)
+    }
+
+    /// Parses `impact <die> <min speed> <damage per m/s>` and
+    /// `face <die> <value> <effect> <args..>` lines. `#` starts a comment.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut effects = Self::default();
+
+        for line in data_lines(text) {
+            let bad_line = || line.error();
+            let kind = |word: &str| {
+                DiceKind::ALL
+                    .into_iter()
+                    .find(|kind| kind.name() == word)
+                    .ok_or_else(bad_line)
+            };
+            let num = |word: &str| line.parse::<f32>(word);
+            let int = |word: &str| line.parse::<i32>(word);
+
+            match line.words.as_slice() {
+                ["impact", die, min_speed, damage_per_speed] => {
+                    effects.impacts.insert(
+                        kind(die)?,
+                        ImpactDef {
+                            min_speed: num(min_speed)?,
+                            damage_per_speed: num(damage_per_speed)?,
+                        },
+                    );
+                }
+                ["face", die, value, effect @ ..] => {
+                    let effect = match effect {
+                        ["explosion", radius, damage] => FaceEffect::Explosion {
+                            radius: num(radius)?,
+                            damage: int(damage)?,
+                        },
+                        ["curse", damage] => FaceEffect::Curse {
+                            damage: int(damage)?,
+                        },
+                        ["heal", amount] => FaceEffect::Heal {
+                            amount: int(amount)?,
+                        },
+                        _ => return Err(bad_line()),
+                    };
+                    effects.faces.insert((kind(die)?, int(value)?), effect);
+                }
+                _ => return Err(bad_line()),
+            }
+        }
+
+        Ok(effects)
+    }
+}
+
+pub(crate) fn load_dice_effects(mut commands: Commands) {
+    let effects = DiceEffects::load("assets/dice/effects.txt");
+    commands.insert_resource(expect_data("dice effects", effects));
+}
+
+/// Dice in flight hurt whatever they hit, harder the faster they were going. Not during an
+/// encounter, where the attack dice would hurt whoever they land on.
+pub(crate) fn dice_impact_system(
+    dice_query: Query<(&Dice, &RollPart)>,
+    group_query: Query<&RollGroup>,
+    health_query: Query<(), With<Health>>,
+    effects: Res<DiceEffects>,
+    mut collision_events: EventReader<CollisionEvent>,
+    mut damage_events: EventWriter<DamageEvent>,
+) {
+    for ev in collision_events.read() {
+        let CollisionEvent::Started(a, b, _) = ev else {
+            continue;
+        };
+
+        for (die, other) in [(*a, *b), (*b, *a)] {
+            let Ok((dice, part)) = dice_query.get(die) else {
+                continue;
+            };
+            let Some(impact) = effects.impacts.get(&dice.kind) else {
+                continue;
+            };
+            let roller = group_query.get(part.0).ok().map(|group| group.roller);
+
+            if dice.rolled || Some(other) == roller || !health_query.contains(other) {
+                continue;
+            }
+
+            // `speed` is from before the physics step that reported the hit.
+            let damage = ((dice.speed - impact.min_speed) * impact.damage_per_speed).round() as i32;
+            if damage > 0 {
+                damage_events.send(DamageEvent {
+                    target: other,
+                    amount: damage,
+                    source: roller,
+                });
+            }
+        }
+    }
+}
+
+pub(crate) fn face_effect_listener(
+    mut health_query: Query<(Entity, &Transform, &mut Health)>,
+    effects: Res<DiceEffects>,
+    mut events: EventReader<DiceRollEvent>,
+    mut damage_events: EventWriter<DamageEvent>,
+    mut trauma_events: EventWriter<CameraTraumaEvent>,
+    mut add_message_event: EventWriter<AddUiMessageEvent>,
+) {
+    for ev in events.read() {
+        if ev.rerolled || !ev.purpose.has_face_effects() {
+            continue;
+        }
+        let Some(effect) = effects.faces.get(&(ev.kind, ev.value)) else {
+            continue;
+        };
+
+        let message = match effect {
+            FaceEffect::Explosion { radius, damage } => {
+                for (entity, xform, _) in health_query.iter() {
+                    if entity != ev.roller && xform.translation.distance(ev.position) <= *radius {
+                        damage_events.send(DamageEvent {
+                            target: entity,
+                            amount: *damage,
+                            source: Some(ev.roller),
+                        });
+                    }
+                }
+                trauma_events.send(CameraTraumaEvent { amount: 0.6 });
+                "The die explodes!"
+            }
+            FaceEffect::Curse { damage } => {
+                damage_events.send(DamageEvent {
+                    target: ev.roller,
+                    amount: *damage,
+                    source: Some(ev.die),
+                });
+                "The die curses you!"
+            }
+            FaceEffect::Heal { amount } => {
+                if let Ok((_, _, mut health)) = health_query.get_mut(ev.roller) {
+                    health.current = (health.current + amount).min(health.max);
+                }
+                "The die heals you!"
+            }
+        };
+
+        add_message_event.send(AddUiMessageEvent {
+            message: format!(
+                "{} {}: {}",
+                ev.kind.name(),
+                ev.kind.label(ev.value),
+                message
+            ),
+            duration: 2.0,
+        });
+    }
+}
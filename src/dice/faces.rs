@@ -0,0 +1,210 @@
+use std::collections::HashMap;
+
+use bevy::{prelude::*, render::mesh::VertexAttributeValues};
+
+use crate::utils::{data_lines, load_data};
+
+use super::{DiceFace, DiceKind};
+
+/// Faces smaller than this fraction of the largest one are bevels, not numbered faces.
+const MIN_FACE_AREA: f32 = 0.5;
+/// Triangles whose normals are closer than this belong to the same face.
+const SAME_FACE_DOT: f32 = 0.999;
+
+/// Which value is printed on each tile of a texture atlas, read from a `.faces` file.
+pub struct FaceSidecar {
+    pub atlas_size: Vec2,
+    pub tile_size: f32,
+    pub tiles: HashMap<(u32, u32), i32>,
+}
+
+impl FaceSidecar {
+    pub fn load(path: &str) -> Result<Self, String> {
+        load_data(path, Self::parse)
+    }
+
+    /// Parses an `atlas <width> <height> <tile size>` line followed by
+    /// `<column> <row> <value>` lines. `#` starts a comment.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut atlas: Option<(Vec2, f32)> = None;
+        let mut tiles = HashMap::new();
+
+        for line in data_lines(text) {
+            match line.words.as_slice() {
+                ["atlas", width, height, tile] => {
+                    let num = |word: &str| line.parse::<f32>(word);
+                    atlas = Some((Vec2::new(num(width)?, num(height)?), num(tile)?));
+                }
+                [column, row, value] => {
+                    let column = line.parse(column)?;
+                    let row = line.parse(row)?;
+                    let value = line.parse(value)?;
+                    tiles.insert((column, row), value);
+                }
+                _ => return Err(line.error()),
+            }
+        }
+
+        let (atlas_size, tile_size) = atlas.ok_or("missing `atlas` line")?;
+
+        Ok(Self {
+            atlas_size,
+            tile_size,
+            tiles,
+        })
+    }
+
+    fn value_at(&self, uv: Vec2) -> Option<i32> {
+        let pixel = uv * self.atlas_size / self.tile_size;
+        self.tiles
+            .get(&(pixel.x.floor() as u32, pixel.y.floor() as u32))
+            .copied()
+    }
+}
+
+struct FaceGroup {
+    normal: Vec3,
+    area: f32,
+    /// Area weighted sum of triangle UV centroids.
+    uv: Vec2,
+}
+
+/// Groups the mesh's triangles into flat faces and reads each face's value off the
+/// atlas tile its UVs land in.
+pub fn faces_from_mesh(mesh: &Mesh, sidecar: &FaceSidecar) -> Result<Vec<DiceFace>, String> {
+    let Some(positions) = mesh
+        .attribute(Mesh::ATTRIBUTE_POSITION)
+        .and_then(|values| values.as_float3())
+    else {
+        return Err(String::from("mesh has no positions"));
+    };
+    let Some(VertexAttributeValues::Float32x2(uvs)) = mesh.attribute(Mesh::ATTRIBUTE_UV_0) else {
+        return Err(String::from("mesh has no UVs"));
+    };
+
+    let indices: Vec<usize> = match mesh.indices() {
+        Some(indices) => indices.iter().collect(),
+        None => (0..positions.len()).collect(),
+    };
+
+    let mut groups: Vec<FaceGroup> = Vec::new();
+
+    for tri in indices.chunks_exact(3) {
+        let [a, b, c] = [tri[0], tri[1], tri[2]].map(|i| Vec3::from(positions[i]));
+        let cross = (b - a).cross(c - a);
+        let area = cross.length() * 0.5;
+
+        if area <= f32::EPSILON {
+            continue;
+        }
+
+        let normal = cross.normalize();
+        let uv =
+            (Vec2::from(uvs[tri[0]]) + Vec2::from(uvs[tri[1]]) + Vec2::from(uvs[tri[2]])) / 3.0;
+
+        match groups
+            .iter_mut()
+            .find(|g| g.normal.normalize().dot(normal) >= SAME_FACE_DOT)
+        {
+            Some(group) => {
+                group.normal += normal * area;
+                group.area += area;
+                group.uv += uv * area;
+            }
+            None => groups.push(FaceGroup {
+                normal: normal * area,
+                area,
+                uv: uv * area,
+            }),
+        }
+    }
+
+    let largest = groups.iter().map(|g| g.area).fold(0.0, f32::max);
+
+    groups
+        .iter()
+        .filter(|g| g.area >= largest * MIN_FACE_AREA)
+        .map(|g| {
+            let uv = g.uv / g.area;
+            let value = sidecar
+                .value_at(uv)
+                .ok_or_else(|| format!("no value for the atlas tile at uv {:?}", uv))?;
+
+            Ok(DiceFace {
+                normal: g.normal.normalize(),
+                value,
+            })
+        })
+        .collect()
+}
+
+/// Checks that a die shows every value once and that opposite faces add up like printed dice.
+pub fn validate_faces(kind: DiceKind, faces: &[DiceFace]) -> Result<(), String> {
+    let expected = kind.values();
+    let mut values: Vec<i32> = faces.iter().map(|f| f.value).collect();
+    values.sort();
+
+    if values != expected {
+        return Err(format!(
+            "{} has faces {:?}, expected {:?}",
+            kind.name(),
+            values,
+            expected
+        ));
+    }
+
+    let total = expected[0] + expected[expected.len() - 1];
+    for face in faces {
+        let opposite = faces
+            .iter()
+            .find(|other| other.normal.dot(face.normal) < -SAME_FACE_DOT);
+
+        if let Some(opposite) = opposite {
+            if face.value + opposite.value != total {
+                return Err(format!(
+                    "{} has {} opposite {}, they should add up to {}",
+                    kind.name(),
+                    face.value,
+                    opposite.value,
+                    total
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shipped_d6_faces_match_their_pips() {
+        let bytes = std::fs::read("assets/meshes/dice.obj").unwrap();
+        let mesh = bevy_obj::load_obj_from_bytes(&bytes).unwrap();
+        let sidecar = FaceSidecar::load("assets/meshes/dice.faces").unwrap();
+
+        let faces = faces_from_mesh(&mesh, &sidecar).unwrap();
+        validate_faces(DiceKind::D6, &faces).unwrap();
+
+        let expected = [
+            (1, Vec3::Y),
+            (6, Vec3::NEG_Y),
+            (2, Vec3::NEG_X),
+            (5, Vec3::X),
+            (4, Vec3::Z),
+            (3, Vec3::NEG_Z),
+        ];
+        for (value, normal) in expected {
+            let face = faces.iter().find(|face| face.value == value).unwrap();
+            assert!(
+                face.normal.dot(normal) > SAME_FACE_DOT,
+                "{} faces {:?}, expected {:?}",
+                value,
+                face.normal,
+                normal
+            );
+        }
+    }
+}
@@ -0,0 +1,429 @@
+use bevy::{color::palettes::tailwind, prelude::*};
+
+use crate::{rng::GameRng, AddUiMessageEvent, GameResourceHandles, GameUi};
+
+use super::{items::DiceItem, DiceKind, DiceRollEvent, ExpressionRolledEvent, RollPurpose};
+
+/// One die from one roll.
+#[derive(Clone, Debug)]
+pub struct RollRecord {
+    /// Seconds since the game started.
+    pub time: f64,
+    pub sides: u32,
+    pub value: i32,
+    pub purpose: RollPurpose,
+    /// Thrown as a real die rather than rolled from the RNG.
+    pub physical: bool,
+    pub cocked: bool,
+    pub rerolled: bool,
+    /// A loaded or magic die, whose faces don't say anything about fairness.
+    pub item: Option<DiceItem>,
+    pub seed: u64,
+}
+
+/// How often each face of a die came up, and whether that looks fair.
+pub struct FaceStats {
+    pub kind: DiceKind,
+    pub rolls: u32,
+    /// `(face, times it came up)`, lowest face first.
+    pub counts: Vec<(i32, u32)>,
+    pub chi_squared: f32,
+    /// Chance of a fair die doing at least this badly. Below 0.05 is suspicious.
+    pub p_value: f32,
+}
+
+/// Every die rolled this session, oldest first.
+#[derive(Resource, Default)]
+pub struct RollHistory {
+    pub records: Vec<RollRecord>,
+    /// Dice thrown out of sight, like a gambler's Liar's dice. They join `records` once
+    /// their die is shown or gone, so the panel can't give them away.
+    pub hidden: Vec<(Entity, RollRecord)>,
+}
+
+impl RollHistory {
+    /// Face counts and a chi-squared test for the plain physical dice of `kind`, `None` if
+    /// it was never thrown.
+    pub fn face_stats(&self, kind: DiceKind) -> Option<FaceStats> {
+        let values = kind.values();
+        let mut counts: Vec<(i32, u32)> = values.iter().map(|v| (*v, 0)).collect();
+
+        for record in self.records.iter() {
+            if !record.physical
+                || record.rerolled
+                || record.item.is_some()
+                || record.sides != kind.sides()
+            {
+                continue;
+            }
+            if let Some((_, count)) = counts.iter_mut().find(|(v, _)| *v == record.value) {
+                *count += 1;
+            }
+        }
+
+        let rolls: u32 = counts.iter().map(|(_, count)| count).sum();
+        if rolls == 0 {
+            return None;
+        }
+
+        let expected = rolls as f32 / values.len() as f32;
+        let chi_squared = counts
+            .iter()
+            .map(|(_, count)| (*count as f32 - expected).powi(2) / expected)
+            .sum();
+        let p_value = chi_squared_p_value(chi_squared, values.len() as f32 - 1.0);
+
+        Some(FaceStats {
+            kind,
+            rolls,
+            counts,
+            chi_squared,
+            p_value,
+        })
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from("time,die,value,purpose,physical,cocked,rerolled,item,seed\n");
+
+        for r in self.records.iter() {
+            out += &format!(
+                "{:.3},d{},{},{},{},{},{},{},{}\n",
+                r.time,
+                r.sides,
+                r.value,
+                purpose_name(&r.purpose),
+                r.physical,
+                r.cocked,
+                r.rerolled,
+                item_name(&r.item),
+                r.seed
+            );
+        }
+
+        out
+    }
+
+    pub fn to_json(&self) -> String {
+        let records: Vec<String> = self
+            .records
+            .iter()
+            .map(|r| {
+                format!(
+                    "  {{\"time\": {:.3}, \"die\": \"d{}\", \"value\": {}, \"purpose\": \"{}\", \
+                     \"physical\": {}, \"cocked\": {}, \"rerolled\": {}, \"item\": \"{}\", \
+                     \"seed\": {}}}",
+                    r.time,
+                    r.sides,
+                    r.value,
+                    purpose_name(&r.purpose),
+                    r.physical,
+                    r.cocked,
+                    r.rerolled,
+                    item_name(&r.item),
+                    r.seed
+                )
+            })
+            .collect();
+
+        format!("[\n{}\n]\n", records.join(",\n"))
+    }
+}
+
+fn purpose_name(purpose: &RollPurpose) -> String {
+    match purpose {
+        RollPurpose::Check(_) => String::from("check"),
+        RollPurpose::Gamble { .. } => String::from("gamble"),
+        _ => format!("{:?}", purpose).to_lowercase(),
+    }
+}
+
+fn item_name(item: &Option<DiceItem>) -> &'static str {
+    item.map_or("", |item| item.def().name)
+}
+
+/// Upper tail of the chi-squared distribution, via the Wilson-Hilferty normal approximation.
+pub fn chi_squared_p_value(chi_squared: f32, degrees_of_freedom: f32) -> f32 {
+    if degrees_of_freedom <= 0.0 {
+        return 1.0;
+    }
+
+    let k = degrees_of_freedom;
+    let z = ((chi_squared / k).cbrt() - (1.0 - 2.0 / (9.0 * k))) / (2.0 / (9.0 * k)).sqrt();
+
+    1.0 - normal_cdf(z)
+}
+
+fn normal_cdf(z: f32) -> f32 {
+    0.5 * (1.0 + erf(z / std::f32::consts::SQRT_2))
+}
+
+/// Abramowitz and Stegun 7.1.26, good to about 1.5e-7.
+fn erf(x: f32) -> f32 {
+    let sign = x.signum();
+    let x = x.abs();
+    let t = 1.0 / (1.0 + 0.327_591_1 * x);
+    let poly = t
+        * (0.254_829_6
+            + t * (-0.284_496_74 + t * (1.421_413_7 + t * (-1.453_152_1 + t * 1.061_405_4))));
+
+    sign * (1.0 - poly * (-x * x).exp())
+}
+
+#[derive(Component)]
+pub(crate) struct RollHistoryPanel;
+
+pub(crate) fn record_rolls_listener(
+    mut history: ResMut<RollHistory>,
+    visibility_query: Query<&Visibility>,
+    rng: Res<GameRng>,
+    time: Res<Time>,
+    mut dice_roll_events: EventReader<DiceRollEvent>,
+    mut rolled_events: EventReader<ExpressionRolledEvent>,
+) {
+    let now = time.elapsed_seconds_f64();
+
+    for ev in dice_roll_events.read() {
+        let record = RollRecord {
+            time: now,
+            sides: ev.kind.sides(),
+            value: ev.value,
+            purpose: ev.purpose.clone(),
+            physical: true,
+            cocked: ev.cocked,
+            rerolled: ev.rerolled,
+            item: ev.item,
+            seed: rng.seed(),
+        };
+
+        match visibility_query.get(ev.die) {
+            Ok(Visibility::Hidden) => history.hidden.push((ev.die, record)),
+            _ => history.records.push(record),
+        }
+    }
+
+    // Hidden dice are recorded once they are revealed, or cleared away unseen
+    let history = history.as_mut();
+    let mut still_hidden = Vec::new();
+    for (die, record) in history.hidden.drain(..) {
+        match visibility_query.get(die) {
+            Ok(Visibility::Hidden) => still_hidden.push((die, record)),
+            _ => history.records.push(record),
+        }
+    }
+    history.hidden = still_hidden;
+
+    // Physical rolls were recorded die by die above
+    for ev in rolled_events.read().filter(|ev| ev.group.is_none()) {
+        for die in ev.result.dice.iter() {
+            history.records.push(RollRecord {
+                time: now,
+                sides: die.sides,
+                value: die.value,
+                purpose: ev.purpose.clone(),
+                physical: false,
+                cocked: false,
+                rerolled: die.rerolled,
+                item: None,
+                seed: rng.seed(),
+            });
+        }
+    }
+}
+
+/// H shows the history panel, F5 and F6 save the history as CSV and JSON.
+pub(crate) fn roll_history_panel_system(
+    mut commands: Commands,
+    mut panel_query: Query<(Entity, &mut Text), With<RollHistoryPanel>>,
+    history: Res<RollHistory>,
+    rng: Res<GameRng>,
+    ui: Res<GameUi>,
+    resources: Res<GameResourceHandles>,
+    key: Res<ButtonInput<KeyCode>>,
+    mut add_message_event: EventWriter<AddUiMessageEvent>,
+) {
+    let exports: [(KeyCode, &str, fn(&RollHistory) -> String); 2] = [
+        (KeyCode::F5, "roll_history.csv", RollHistory::to_csv),
+        (KeyCode::F6, "roll_history.json", RollHistory::to_json),
+    ];
+    for (key_code, path, export) in exports {
+        if !key.just_pressed(key_code) {
+            continue;
+        }
+
+        let message = match std::fs::write(path, export(&history)) {
+            Ok(_) => format!("Saved {} rolls to {}", history.records.len(), path),
+            Err(e) => format!("Can't save {}: {}", path, e),
+        };
+        add_message_event.send(AddUiMessageEvent {
+            message,
+            duration: 2.0,
+        });
+    }
+
+    if key.just_pressed(KeyCode::KeyH) {
+        match panel_query.get_single() {
+            Ok((entity, _)) => commands.entity(entity).despawn(),
+            Err(_) => {
+                let Some(root) = ui.ui_entity else {
+                    return;
+                };
+                let panel = commands
+                    .spawn(
+                        TextBundle::from_section(
+                            "",
+                            TextStyle {
+                                font: resources.font.clone(),
+                                font_size: 20.0,
+                                color: tailwind::YELLOW_100.into(),
+                            },
+                        )
+                        .with_style(Style {
+                            position_type: PositionType::Absolute,
+                            left: Val::Px(12.0),
+                            top: Val::Px(12.0),
+                            ..default()
+                        })
+                        .with_background_color(Color::srgba(0.0, 0.0, 0.0, 0.6)),
+                    )
+                    .insert(RollHistoryPanel)
+                    .id();
+                commands.entity(root).add_child(panel);
+            }
+        }
+        return;
+    }
+
+    let Ok((_, mut text)) = panel_query.get_single_mut() else {
+        return;
+    };
+    if !history.is_changed() && !text.sections[0].value.is_empty() {
+        return;
+    }
+
+    let mut lines = vec![format!(
+        "Roll history: {} dice, seed {} (F5 csv, F6 json)",
+        history.records.len(),
+        rng.seed()
+    )];
+
+    for kind in DiceKind::ALL {
+        let Some(stats) = history.face_stats(kind) else {
+            continue;
+        };
+
+        let counts: Vec<String> = stats
+            .counts
+            .iter()
+            .map(|(face, count)| format!("{}:{}", kind.label(*face), count))
+            .collect();
+        let verdict = if stats.p_value < 0.05 {
+            "suspicious"
+        } else {
+            "looks fair"
+        };
+
+        lines.push(format!(
+            "{} x{}  {}  chi2 {:.1}, p {:.2} {}",
+            kind.name(),
+            stats.rolls,
+            counts.join(" "),
+            stats.chi_squared,
+            stats.p_value,
+            verdict
+        ));
+    }
+
+    let recent: Vec<String> = history
+        .records
+        .iter()
+        .rev()
+        .take(10)
+        .map(|r| format!("d{} {}", r.sides, r.value))
+        .collect();
+    if !recent.is_empty() {
+        lines.push(format!("Last: {}", recent.join(", ")));
+    }
+
+    text.sections[0].value = lines.join("\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(value: i32) -> RollRecord {
+        RollRecord {
+            time: 0.0,
+            sides: 6,
+            value,
+            purpose: RollPurpose::Casual,
+            physical: true,
+            cocked: false,
+            rerolled: false,
+            item: None,
+            seed: 0,
+        }
+    }
+
+    #[test]
+    fn p_value_at_a_known_point() {
+        // The 5% critical value for five degrees of freedom
+        let p = chi_squared_p_value(11.07, 5.0);
+        assert!((p - 0.05).abs() < 0.005, "{}", p);
+    }
+
+    #[test]
+    fn uniform_sample_looks_fair() {
+        let history = RollHistory {
+            records: (0..60).map(|i| record(i % 6 + 1)).collect(),
+            ..default()
+        };
+
+        let stats = history.face_stats(DiceKind::D6).unwrap();
+        assert_eq!(stats.rolls, 60);
+        assert!(stats.counts.iter().all(|(_, count)| *count == 10));
+        assert_eq!(stats.chi_squared, 0.0);
+        assert!(stats.p_value > 0.99, "{}", stats.p_value);
+    }
+
+    #[test]
+    fn loaded_sample_looks_suspicious() {
+        let history = RollHistory {
+            records: (0..60)
+                .map(|i| record(if i % 2 == 0 { 6 } else { i % 6 + 1 }))
+                .collect(),
+            ..default()
+        };
+
+        assert!(history.face_stats(DiceKind::D6).unwrap().p_value < 0.05);
+    }
+
+    #[test]
+    fn face_stats_skip_dice_that_say_nothing_about_fairness() {
+        let mut history = RollHistory {
+            records: vec![record(3)],
+            ..default()
+        };
+        history.records.push(RollRecord {
+            rerolled: true,
+            ..record(6)
+        });
+        history.records.push(RollRecord {
+            item: Some(DiceItem::LoadedD6),
+            ..record(6)
+        });
+        history.records.push(RollRecord {
+            physical: false,
+            ..record(6)
+        });
+        history.records.push(RollRecord {
+            sides: 8,
+            ..record(6)
+        });
+
+        let stats = history.face_stats(DiceKind::D6).unwrap();
+        assert_eq!(stats.rolls, 1);
+        assert_eq!(stats.counts[2], (3, 1));
+        assert!(history.face_stats(DiceKind::D20).is_none());
+    }
+}
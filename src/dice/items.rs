@@ -0,0 +1,148 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use crate::player::components::Dice;
+
+use super::{DiceKind, DiceSet};
+
+/// Special dice found as loot, thrown one at a time instead of the plain pool.
+#[derive(Eq, PartialEq, Hash, Clone, Copy, Debug)]
+pub enum DiceItem {
+    LoadedD6,
+    RubberD6,
+    LeadD20,
+    LuckyD20,
+    CheatersD6,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DiceMagic {
+    /// Throws itself again when it lands on a 1.
+    RerollOnes,
+    /// Always turns up this face once it stops.
+    FixedFace(i32),
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct DiceItemDef {
+    pub name: &'static str,
+    pub kind: DiceKind,
+    /// Face the weight is hidden behind the opposite side of, so it tends to come up.
+    pub loaded_toward: Option<i32>,
+    pub mass: f32,
+    pub restitution: f32,
+    pub friction: f32,
+    pub magic: Option<DiceMagic>,
+}
+
+/// How far off centre a loaded die's weight sits, as a fraction of its radius.
+const LOAD_OFFSET: f32 = 0.6;
+
+impl DiceItem {
+    pub const ALL: [DiceItem; 5] = [
+        DiceItem::LoadedD6,
+        DiceItem::RubberD6,
+        DiceItem::LeadD20,
+        DiceItem::LuckyD20,
+        DiceItem::CheatersD6,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        DiceItem::ALL
+            .into_iter()
+            .find(|item| item.def().name.eq_ignore_ascii_case(name))
+    }
+
+    pub fn def(&self) -> DiceItemDef {
+        match self {
+            DiceItem::LoadedD6 => DiceItemDef {
+                name: "loaded d6",
+                kind: DiceKind::D6,
+                loaded_toward: Some(6),
+                mass: 9.0,
+                restitution: 0.3,
+                friction: 0.5,
+                magic: None,
+            },
+            DiceItem::RubberD6 => DiceItemDef {
+                name: "rubber d6",
+                kind: DiceKind::D6,
+                loaded_toward: None,
+                mass: 6.0,
+                restitution: 0.9,
+                friction: 1.0,
+                magic: None,
+            },
+            DiceItem::LeadD20 => DiceItemDef {
+                name: "lead d20",
+                kind: DiceKind::D20,
+                loaded_toward: None,
+                mass: 30.0,
+                restitution: 0.05,
+                friction: 0.8,
+                magic: None,
+            },
+            DiceItem::LuckyD20 => DiceItemDef {
+                name: "lucky d20",
+                kind: DiceKind::D20,
+                loaded_toward: None,
+                mass: 9.0,
+                restitution: 0.3,
+                friction: 0.5,
+                magic: Some(DiceMagic::RerollOnes),
+            },
+            DiceItem::CheatersD6 => DiceItemDef {
+                name: "cheater's d6",
+                kind: DiceKind::D6,
+                loaded_toward: None,
+                mass: 9.0,
+                restitution: 0.3,
+                friction: 0.5,
+                magic: Some(DiceMagic::FixedFace(6)),
+            },
+        }
+    }
+}
+
+/// Turns a freshly thrown plain die into `item`, replacing its [`Dice`] and mass and
+/// giving it the item's bounce and grip.
+pub fn make_item_die(commands: &mut Commands, dice_set: &DiceSet, die: Entity, item: DiceItem) {
+    let def = item.def();
+
+    // The weight sits behind the face opposite the favoured one.
+    let centre_of_mass = def
+        .loaded_toward
+        .and_then(|value| {
+            dice_set
+                .get(def.kind)
+                .faces
+                .iter()
+                .find(|face| face.value == value)
+        })
+        .map_or(Vec3::ZERO, |face| {
+            -face.normal * def.kind.radius() * LOAD_OFFSET
+        });
+
+    // The hull's own inertia at the item's weight, only the centre of mass moves.
+    let hull =
+        MassProperties::from_rapier(dice_set.get(def.kind).collider.raw.mass_properties(1.0));
+    let scale = def.mass / hull.mass;
+
+    commands.entity(die).insert((
+        Dice {
+            kind: def.kind,
+            item: Some(item),
+            ..Dice::default()
+        },
+        ColliderMassProperties::MassProperties(MassProperties {
+            local_center_of_mass: centre_of_mass,
+            mass: def.mass,
+            principal_inertia: hull.principal_inertia * scale,
+            principal_inertia_local_frame: hull.principal_inertia_local_frame,
+        }),
+        // All of the item's weight is in the collider above.
+        AdditionalMassProperties::Mass(0.0),
+        Restitution::coefficient(def.restitution),
+        Friction::coefficient(def.friction),
+    ));
+}
@@ -0,0 +1,787 @@
+//! Standard RPG dice notation, e.g. `3d6+2`, `4d6kh3`, `2d20kl1`, `1d6!`, `2d8ro<2`.
+//!
+//! | Syntax        | Meaning                                              |
+//! |---------------|------------------------------------------------------|
+//! | `NdS`, `d%`   | Roll N (default 1) dice with S sides, `%` is 100     |
+//! | `khN`, `klN`  | Keep the highest / lowest N dice (`kN` is `khN`)     |
+//! | `dhN`, `dlN`  | Drop the highest / lowest N dice                     |
+//! | `!`, `!>N`    | Explode: roll again on max (or on N and above)       |
+//! | `rN`, `r<N`   | Reroll while the die shows N (or N and below)        |
+//! | `roN`, `ro<N` | Reroll once                                          |
+//! | `+ - * / ()`  | Arithmetic, division rounds down                     |
+
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+};
+
+use rand::Rng;
+
+use super::DiceKind;
+
+/// Stops exploding and rerolling dice that always match, like `1d1!`.
+const MAX_REPEATS: u32 = 100;
+const MAX_DICE: u32 = 1000;
+const MAX_SIDES: u32 = 1000;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Compare {
+    Equal(i32),
+    AtMost(i32),
+    AtLeast(i32),
+}
+
+impl Compare {
+    pub fn matches(&self, value: i32) -> bool {
+        match *self {
+            Compare::Equal(n) => value == n,
+            Compare::AtMost(n) => value <= n,
+            Compare::AtLeast(n) => value >= n,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Keep {
+    Highest(u32),
+    Lowest(u32),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Reroll {
+    pub when: Compare,
+    pub once: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DiceTerm {
+    pub count: u32,
+    pub sides: u32,
+    pub keep: Option<Keep>,
+    pub explode: Option<Compare>,
+    pub reroll: Option<Reroll>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DiceExpr {
+    Number(i32),
+    Dice(DiceTerm),
+    Neg(Box<DiceExpr>),
+    Binary(Box<DiceExpr>, Op, Box<DiceExpr>),
+}
+
+#[derive(Clone, Debug)]
+pub struct DieRoll {
+    pub sides: u32,
+    pub value: i32,
+    /// Dropped dice don't count towards the total.
+    pub kept: bool,
+    pub rerolled: bool,
+    /// Extra die rolled because the one before it exploded.
+    pub exploded: bool,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct RollResult {
+    pub total: i32,
+    pub dice: Vec<DieRoll>,
+}
+
+impl RollResult {
+    /// Kept dice showing `target` or more.
+    pub fn successes(&self, target: i32) -> u32 {
+        self.dice
+            .iter()
+            .filter(|die| die.kept && die.value >= target)
+            .count() as u32
+    }
+
+    /// Faces in roll order, dropped dice in brackets, e.g. `[6, 5, 3, (1)] = 14`.
+    pub fn describe(&self) -> String {
+        let faces: Vec<String> = self
+            .dice
+            .iter()
+            .map(|die| match (die.kept, die.exploded) {
+                (false, _) => format!("({})", die.value),
+                (true, true) => format!("{}!", die.value),
+                (true, false) => die.value.to_string(),
+            })
+            .collect();
+
+        format!("[{}] = {}", faces.join(", "), self.total)
+    }
+}
+
+/// Something that can roll a die with the given number of sides.
+/// Returns `None` when it has no result for it yet.
+pub trait DieSource {
+    fn roll(&mut self, sides: u32) -> Option<i32>;
+
+    /// The last roll was thrown away by a reroll.
+    fn discard(&mut self) {}
+}
+
+/// Rolls straight from a random number generator.
+pub struct RngDice<'a, R: Rng>(pub &'a mut R);
+
+impl<'a, R: Rng> DieSource for RngDice<'a, R> {
+    fn roll(&mut self, sides: u32) -> Option<i32> {
+        Some(self.0.gen_range(1..=sides.clamp(1, MAX_SIDES) as i32))
+    }
+}
+
+/// Hands out the faces of physical dice in the order they were thrown. Dice that don't
+/// exist in the set (a d3, say) fall back to `rng`.
+pub struct ThrownDice<'a, R: Rng> {
+    /// Index into the thrown dice and the face it shows, per kind.
+    faces: HashMap<DiceKind, VecDeque<(usize, i32)>>,
+    rng: &'a mut R,
+    /// Thrown dice used by the last roll.
+    last: Vec<usize>,
+    /// The first die that was asked for but hasn't been thrown yet.
+    pub missing: Option<DiceKind>,
+    /// Thrown dice whose faces were rerolled away.
+    pub discarded: Vec<usize>,
+}
+
+impl<'a, R: Rng> ThrownDice<'a, R> {
+    pub fn new(thrown: &[(DiceKind, i32)], rng: &'a mut R) -> Self {
+        let mut faces: HashMap<DiceKind, VecDeque<(usize, i32)>> = HashMap::new();
+        for (i, (kind, value)) in thrown.iter().enumerate() {
+            faces.entry(*kind).or_default().push_back((i, *value));
+        }
+
+        Self {
+            faces,
+            rng,
+            last: Vec::new(),
+            missing: None,
+            discarded: Vec::new(),
+        }
+    }
+
+    fn take(&mut self, kind: DiceKind) -> Option<i32> {
+        let Some((index, value)) = self.faces.get_mut(&kind).and_then(|q| q.pop_front()) else {
+            if self.missing.is_none() {
+                self.missing = Some(kind);
+            }
+            return None;
+        };
+
+        self.last.push(index);
+        Some(value)
+    }
+}
+
+impl<'a, R: Rng> DieSource for ThrownDice<'a, R> {
+    fn roll(&mut self, sides: u32) -> Option<i32> {
+        self.last.clear();
+
+        // A percentile roll is the tens die plus a d10, where 00 and 0 make 100.
+        if sides == 100 {
+            let tens = self.take(DiceKind::D100)?;
+            let units = self.take(DiceKind::D10)?;
+            let value = tens % 100 + units % 10;
+            return Some(if value == 0 { 100 } else { value });
+        }
+
+        match DiceKind::from_sides(sides) {
+            Some(kind) => self.take(kind),
+            None => RngDice(self.rng).roll(sides),
+        }
+    }
+
+    fn discard(&mut self) {
+        self.discarded.append(&mut self.last);
+    }
+}
+
+impl DiceExpr {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let text = text.to_lowercase();
+        let mut parser = Parser {
+            text: text.as_bytes(),
+            pos: 0,
+        };
+
+        let expr = parser.expr()?;
+        match parser.peek() {
+            None => Ok(expr),
+            Some(c) => Err(parser.error(&format!("unexpected `{}`", c as char))),
+        }
+    }
+
+    pub fn roll<R: Rng>(&self, rng: &mut R) -> RollResult {
+        // Never runs out of dice.
+        self.evaluate(&mut RngDice(rng)).unwrap()
+    }
+
+    /// `None` if `source` ran out of dice part way through.
+    pub fn evaluate(&self, source: &mut impl DieSource) -> Option<RollResult> {
+        match self {
+            DiceExpr::Number(n) => Some(RollResult {
+                total: *n,
+                dice: Vec::new(),
+            }),
+            DiceExpr::Dice(term) => term.evaluate(source),
+            DiceExpr::Neg(inner) => {
+                let mut result = inner.evaluate(source)?;
+                result.total = result.total.saturating_neg();
+                Some(result)
+            }
+            DiceExpr::Binary(lhs, op, rhs) => {
+                let mut lhs = lhs.evaluate(source)?;
+                let rhs = rhs.evaluate(source)?;
+
+                // Totals saturate rather than wrap, and dividing by zero gives zero
+                // rather than failing a roll.
+                lhs.total = match op {
+                    Op::Add => lhs.total.saturating_add(rhs.total),
+                    Op::Sub => lhs.total.saturating_sub(rhs.total),
+                    Op::Mul => lhs.total.saturating_mul(rhs.total),
+                    Op::Div if rhs.total == 0 => 0,
+                    Op::Div => div_floor(lhs.total, rhs.total),
+                };
+                lhs.dice.extend(rhs.dice);
+                Some(lhs)
+            }
+        }
+    }
+
+    /// Physical dice to throw up front, before any rerolls or explosions.
+    pub fn dice(&self) -> Vec<DiceKind> {
+        match self {
+            DiceExpr::Number(_) => Vec::new(),
+            DiceExpr::Dice(term) => {
+                let kinds = match term.sides {
+                    100 => vec![DiceKind::D100, DiceKind::D10],
+                    sides => DiceKind::from_sides(sides).into_iter().collect(),
+                };
+                (0..term.count).flat_map(|_| kinds.clone()).collect()
+            }
+            DiceExpr::Neg(inner) => inner.dice(),
+            DiceExpr::Binary(lhs, _, rhs) => {
+                let mut dice = lhs.dice();
+                dice.extend(rhs.dice());
+                dice
+            }
+        }
+    }
+}
+
+impl DiceTerm {
+    fn evaluate(&self, source: &mut impl DieSource) -> Option<RollResult> {
+        let mut dice = Vec::new();
+
+        for _ in 0..self.count {
+            let mut value = source.roll(self.sides)?;
+            let mut rerolled = false;
+
+            if let Some(reroll) = self.reroll {
+                let mut tries = 0;
+                while reroll.when.matches(value) && tries < MAX_REPEATS {
+                    source.discard();
+                    value = source.roll(self.sides)?;
+                    rerolled = true;
+                    tries += 1;
+
+                    if reroll.once {
+                        break;
+                    }
+                }
+            }
+
+            dice.push(DieRoll {
+                sides: self.sides,
+                value,
+                kept: true,
+                rerolled,
+                exploded: false,
+            });
+
+            if let Some(explode) = self.explode {
+                let mut last = value;
+                let mut explosions = 0;
+                while explode.matches(last) && explosions < MAX_REPEATS {
+                    last = source.roll(self.sides)?;
+                    explosions += 1;
+
+                    dice.push(DieRoll {
+                        sides: self.sides,
+                        value: last,
+                        kept: true,
+                        rerolled: false,
+                        exploded: true,
+                    });
+                }
+            }
+        }
+
+        if let Some(keep) = self.keep {
+            let mut order: Vec<usize> = (0..dice.len()).collect();
+            order.sort_by_key(|i| dice[*i].value);
+
+            let (keep_count, from_top) = match keep {
+                Keep::Highest(n) => (n as usize, true),
+                Keep::Lowest(n) => (n as usize, false),
+            };
+            if from_top {
+                order.reverse();
+            }
+
+            for i in order.into_iter().skip(keep_count) {
+                dice[i].kept = false;
+            }
+        }
+
+        Some(RollResult {
+            total: dice.iter().filter(|d| d.kept).map(|d| d.value).sum(),
+            dice,
+        })
+    }
+}
+
+/// `a / b` rounded down, towards negative infinity, saturating on overflow. `b` can't be 0.
+fn div_floor(a: i32, b: i32) -> i32 {
+    let Some(quotient) = a.checked_div(b) else {
+        return i32::MAX;
+    };
+
+    if a % b != 0 && (a < 0) != (b < 0) {
+        quotient - 1
+    } else {
+        quotient
+    }
+}
+
+impl fmt::Display for Compare {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Compare::Equal(n) => write!(f, "{}", n),
+            Compare::AtMost(n) => write!(f, "<{}", n),
+            Compare::AtLeast(n) => write!(f, ">{}", n),
+        }
+    }
+}
+
+impl fmt::Display for DiceExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DiceExpr::Number(n) => write!(f, "{}", n),
+            DiceExpr::Dice(term) => {
+                write!(f, "{}d{}", term.count, term.sides)?;
+                match term.reroll {
+                    Some(Reroll { when, once: true }) => write!(f, "ro{}", when)?,
+                    Some(Reroll { when, once: false }) => write!(f, "r{}", when)?,
+                    None => {}
+                }
+                match term.explode {
+                    Some(Compare::AtLeast(n)) if n == term.sides as i32 => write!(f, "!")?,
+                    Some(when) => write!(f, "!{}", when)?,
+                    None => {}
+                }
+                match term.keep {
+                    Some(Keep::Highest(n)) => write!(f, "kh{}", n),
+                    Some(Keep::Lowest(n)) => write!(f, "kl{}", n),
+                    None => Ok(()),
+                }
+            }
+            DiceExpr::Neg(inner) => match **inner {
+                DiceExpr::Binary(..) => write!(f, "-({})", inner),
+                _ => write!(f, "-{}", inner),
+            },
+            DiceExpr::Binary(lhs, op, rhs) => {
+                // Only bracket what would otherwise read differently.
+                let bracket = |side: &DiceExpr, right: bool| match side {
+                    DiceExpr::Binary(_, inner, _) => {
+                        inner.precedence() < op.precedence()
+                            || (right && inner.precedence() == op.precedence())
+                    }
+                    _ => false,
+                };
+                let write_side = |f: &mut fmt::Formatter<'_>, side: &DiceExpr, right: bool| {
+                    if bracket(side, right) {
+                        write!(f, "({})", side)
+                    } else {
+                        write!(f, "{}", side)
+                    }
+                };
+
+                write_side(f, lhs, false)?;
+                let symbol = match op {
+                    Op::Add => "+",
+                    Op::Sub => "-",
+                    Op::Mul => "*",
+                    Op::Div => "/",
+                };
+                write!(f, "{}", symbol)?;
+                write_side(f, rhs, true)
+            }
+        }
+    }
+}
+
+impl Op {
+    fn precedence(&self) -> u8 {
+        match self {
+            Op::Add | Op::Sub => 0,
+            Op::Mul | Op::Div => 1,
+        }
+    }
+}
+
+struct Parser<'a> {
+    text: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, message: &str) -> String {
+        format!("{} at position {}", message, self.pos + 1)
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        while self
+            .text
+            .get(self.pos)
+            .map_or(false, |c| c.is_ascii_whitespace())
+        {
+            self.pos += 1;
+        }
+        self.text.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, c: u8) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expr(&mut self) -> Result<DiceExpr, String> {
+        let mut lhs = self.term()?;
+
+        loop {
+            let op = match self.peek() {
+                Some(b'+') => Op::Add,
+                Some(b'-') => Op::Sub,
+                _ => return Ok(lhs),
+            };
+            self.pos += 1;
+            lhs = DiceExpr::Binary(Box::new(lhs), op, Box::new(self.term()?));
+        }
+    }
+
+    fn term(&mut self) -> Result<DiceExpr, String> {
+        let mut lhs = self.factor()?;
+
+        loop {
+            let op = match self.peek() {
+                Some(b'*') => Op::Mul,
+                Some(b'/') => Op::Div,
+                _ => return Ok(lhs),
+            };
+            self.pos += 1;
+            lhs = DiceExpr::Binary(Box::new(lhs), op, Box::new(self.factor()?));
+        }
+    }
+
+    fn factor(&mut self) -> Result<DiceExpr, String> {
+        match self.peek() {
+            Some(b'-') => {
+                self.pos += 1;
+                Ok(DiceExpr::Neg(Box::new(self.factor()?)))
+            }
+            Some(b'(') => {
+                self.pos += 1;
+                let inner = self.expr()?;
+                if !self.eat(b')') {
+                    return Err(self.error("expected `)`"));
+                }
+                Ok(inner)
+            }
+            Some(b'd') => self.dice(1),
+            Some(c) if c.is_ascii_digit() => {
+                let n = self.number()?;
+                if self.peek() == Some(b'd') {
+                    self.dice(n)
+                } else {
+                    Ok(DiceExpr::Number(self.int(n)?))
+                }
+            }
+            Some(c) => Err(self.error(&format!("unexpected `{}`", c as char))),
+            None => Err(self.error("unexpected end")),
+        }
+    }
+
+    fn number(&mut self) -> Result<u32, String> {
+        self.peek();
+        let start = self.pos;
+        while self
+            .text
+            .get(self.pos)
+            .map_or(false, |c| c.is_ascii_digit())
+        {
+            self.pos += 1;
+        }
+
+        std::str::from_utf8(&self.text[start..self.pos])
+            .ok()
+            .and_then(|digits| digits.parse().ok())
+            .ok_or_else(|| self.error("expected a number"))
+    }
+
+    /// `n` as a total, if it fits.
+    fn int(&self, n: u32) -> Result<i32, String> {
+        i32::try_from(n).map_err(|_| self.error("number too big"))
+    }
+
+    fn compare(&mut self, default: Option<Compare>) -> Result<Compare, String> {
+        match self.peek() {
+            Some(b'<') => {
+                self.pos += 1;
+                let n = self.number()?;
+                Ok(Compare::AtMost(self.int(n)?))
+            }
+            Some(b'>') => {
+                self.pos += 1;
+                let n = self.number()?;
+                Ok(Compare::AtLeast(self.int(n)?))
+            }
+            Some(b'=') => {
+                self.pos += 1;
+                let n = self.number()?;
+                Ok(Compare::Equal(self.int(n)?))
+            }
+            Some(c) if c.is_ascii_digit() => {
+                let n = self.number()?;
+                Ok(Compare::Equal(self.int(n)?))
+            }
+            _ => default.ok_or_else(|| self.error("expected a number")),
+        }
+    }
+
+    fn dice(&mut self, count: u32) -> Result<DiceExpr, String> {
+        self.eat(b'd');
+
+        let sides = if self.eat(b'%') { 100 } else { self.number()? };
+        if sides == 0 {
+            return Err(self.error("dice need at least one side"));
+        }
+        if sides > MAX_SIDES {
+            return Err(self.error(&format!("dice can't have more than {} sides", MAX_SIDES)));
+        }
+        if count > MAX_DICE {
+            return Err(self.error(&format!("can't roll more than {} dice", MAX_DICE)));
+        }
+
+        let mut term = DiceTerm {
+            count,
+            sides,
+            keep: None,
+            explode: None,
+            reroll: None,
+        };
+
+        loop {
+            let all_but = |n: u32| count.saturating_sub(n);
+
+            match self.peek() {
+                Some(b'k') => {
+                    self.pos += 1;
+                    term.keep = Some(if self.eat(b'l') {
+                        Keep::Lowest(self.number()?)
+                    } else {
+                        self.eat(b'h');
+                        Keep::Highest(self.number()?)
+                    });
+                }
+                Some(b'd') => {
+                    self.pos += 1;
+                    term.keep = Some(if self.eat(b'h') {
+                        Keep::Lowest(all_but(self.number()?))
+                    } else {
+                        self.eat(b'l');
+                        Keep::Highest(all_but(self.number()?))
+                    });
+                }
+                Some(b'!') => {
+                    self.pos += 1;
+                    term.explode = Some(self.compare(Some(Compare::AtLeast(sides as i32)))?);
+                }
+                Some(b'r') => {
+                    self.pos += 1;
+                    let once = self.eat(b'o');
+                    term.reroll = Some(Reroll {
+                        when: self.compare(None)?,
+                        once,
+                    });
+                }
+                _ => return Ok(DiceExpr::Dice(term)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Hands out the given faces in order, whatever the sides.
+    struct Faces(VecDeque<i32>);
+
+    impl DieSource for Faces {
+        fn roll(&mut self, _sides: u32) -> Option<i32> {
+            self.0.pop_front()
+        }
+    }
+
+    fn roll(text: &str, faces: &[i32]) -> RollResult {
+        let expr = DiceExpr::parse(text).unwrap();
+        let mut source = Faces(faces.iter().copied().collect());
+        let result = expr.evaluate(&mut source).unwrap();
+        assert!(source.0.is_empty(), "{} left faces unused", text);
+        result
+    }
+
+    fn kept(result: &RollResult) -> Vec<i32> {
+        result
+            .dice
+            .iter()
+            .filter(|die| die.kept)
+            .map(|die| die.value)
+            .collect()
+    }
+
+    #[test]
+    fn sums_dice_and_modifiers() {
+        let result = roll("3d6+2", &[1, 2, 3]);
+        assert_eq!(result.total, 8);
+        assert_eq!(result.dice.len(), 3);
+    }
+
+    #[test]
+    fn keeps_highest() {
+        let result = roll("4d6kh3", &[6, 1, 4, 3]);
+        assert_eq!(result.total, 13);
+        assert_eq!(kept(&result), vec![6, 4, 3]);
+    }
+
+    #[test]
+    fn keeps_lowest() {
+        let result = roll("2d20kl1", &[15, 4]);
+        assert_eq!(result.total, 4);
+        assert_eq!(kept(&result), vec![4]);
+    }
+
+    #[test]
+    fn drops_lowest() {
+        assert_eq!(roll("4d6dl1", &[2, 5, 1, 6]).total, 13);
+    }
+
+    #[test]
+    fn explodes_on_max() {
+        let result = roll("1d6!", &[6, 6, 2]);
+        assert_eq!(result.total, 14);
+        let exploded: Vec<bool> = result.dice.iter().map(|die| die.exploded).collect();
+        assert_eq!(exploded, vec![false, true, true]);
+    }
+
+    #[test]
+    fn explodes_at_threshold() {
+        assert_eq!(roll("1d10!>9", &[9, 10, 3]).total, 22);
+    }
+
+    #[test]
+    fn rerolls_once() {
+        // The first die rerolls its 1 into another 1 and has to keep it.
+        let result = roll("2d8ro<2", &[1, 1, 5]);
+        assert_eq!(result.total, 6);
+        assert!(result.dice[0].rerolled);
+        assert!(!result.dice[1].rerolled);
+    }
+
+    #[test]
+    fn rerolls_until_it_misses() {
+        assert_eq!(roll("1d6r1", &[1, 1, 4]).total, 4);
+    }
+
+    #[test]
+    fn respects_precedence() {
+        assert_eq!(roll("2+3*4", &[]).total, 14);
+        assert_eq!(roll("(2+3)*4", &[]).total, 20);
+        assert_eq!(roll("10-2-3", &[]).total, 5);
+        assert_eq!(roll("-2*3", &[]).total, -6);
+        assert_eq!(roll("7/2", &[]).total, 3);
+        assert_eq!(roll("1d6+2*1d4", &[5, 3]).total, 11);
+    }
+
+    #[test]
+    fn division_rounds_down() {
+        assert_eq!(roll("7/(1-3)", &[]).total, -4);
+        assert_eq!(roll("-7/2", &[]).total, -4);
+        assert_eq!(roll("-7/(0-2)", &[]).total, 3);
+        assert_eq!(roll("-8/2", &[]).total, -4);
+        assert_eq!(roll("1d6/2", &[5]).total, 2);
+    }
+
+    #[test]
+    fn divides_by_zero_to_zero() {
+        assert_eq!(roll("5/0", &[]).total, 0);
+    }
+
+    #[test]
+    fn saturates_instead_of_overflowing() {
+        assert_eq!(roll("100000*100000", &[]).total, i32::MAX);
+        assert_eq!(roll("-100000*100000", &[]).total, i32::MIN);
+        assert_eq!(roll("2147483647+1", &[]).total, i32::MAX);
+    }
+
+    #[test]
+    fn prints_back_what_it_parsed() {
+        for text in ["3d6+2", "4d6kh3", "2d20kl1", "1d6!", "2d8ro<2", "(1+2)*3"] {
+            assert_eq!(DiceExpr::parse(text).unwrap().to_string(), text);
+        }
+    }
+
+    #[test]
+    fn rejects_bad_notation() {
+        for text in [
+            "",
+            "3d",
+            "3d0",
+            "2d6+",
+            "(1+2",
+            "1d6x",
+            "1001d6",
+            "1d3000000000",
+            "1d1001",
+            "3000000000",
+            "1d6!>3000000000",
+        ] {
+            assert!(
+                DiceExpr::parse(text).is_err(),
+                "`{}` should not parse",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn rolls_large_dice_in_range() {
+        let expr = DiceExpr::parse("1d1000").unwrap();
+        let mut rng = rand::rngs::mock::StepRng::new(u64::MAX, 1);
+        let total = expr.roll(&mut rng).total;
+        assert!((1..=1000).contains(&total));
+    }
+}
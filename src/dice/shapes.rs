@@ -0,0 +1,348 @@
+use std::f32::consts::TAU;
+
+use bevy::{
+    prelude::*,
+    render::{mesh::PrimitiveTopology, render_asset::RenderAssetUsages},
+};
+
+const PHI: f32 = 1.618_034;
+
+/// Layout of the label cells in `dice/numbers.png`.
+const ATLAS_COLUMNS: usize = 8;
+const ATLAS_ROWS: usize = 4;
+
+/// Label drawn in each cell of `dice/numbers.png`, left to right, top to bottom.
+pub const ATLAS_LABELS: [&str; 29] = [
+    "1", "2", "3", "4", "5", "6", "7", "8", "9", "10", "11", "12", "13", "14", "15", "16", "17",
+    "18", "19", "20", "0", "00", "30", "40", "50", "60", "70", "80", "90",
+];
+
+/// A convex polyhedron centred on the origin.
+pub struct Polyhedron {
+    pub vertices: Vec<Vec3>,
+    /// Corners of each face, counter-clockwise seen from outside.
+    pub faces: Vec<Vec<Vec3>>,
+}
+
+impl Polyhedron {
+    /// Builds the faces by collecting the vertices furthest along each outward normal.
+    fn from_face_normals(vertices: Vec<Vec3>, normals: &[Vec3]) -> Self {
+        let faces = normals
+            .iter()
+            .map(|normal| {
+                let normal = normal.normalize();
+                let furthest = vertices
+                    .iter()
+                    .map(|v| v.dot(normal))
+                    .fold(f32::MIN, f32::max);
+
+                let mut corners: Vec<Vec3> = vertices
+                    .iter()
+                    .copied()
+                    .filter(|v| v.dot(normal) >= furthest - 1e-3)
+                    .collect();
+
+                let centre = corners.iter().sum::<Vec3>() / corners.len() as f32;
+                let u = (corners[0] - centre).normalize();
+                let w = normal.cross(u);
+                let angle = |p: &Vec3| (*p - centre).dot(w).atan2((*p - centre).dot(u));
+                corners.sort_by(|a, b| angle(a).total_cmp(&angle(b)));
+
+                corners
+            })
+            .collect();
+
+        Self { vertices, faces }
+    }
+
+    /// Uniformly scales the shape so its furthest vertex sits at `radius`.
+    pub fn scaled(mut self, radius: f32) -> Self {
+        let furthest = self.vertices.iter().map(|v| v.length()).fold(0.0, f32::max);
+        let scale = radius / furthest;
+
+        self.vertices.iter_mut().for_each(|v| *v *= scale);
+        self.faces
+            .iter_mut()
+            .flatten()
+            .for_each(|corner| *corner *= scale);
+        self
+    }
+
+    pub fn face_normal(&self, face: usize) -> Vec3 {
+        let corners = &self.faces[face];
+        (corners[1] - corners[0])
+            .cross(corners[2] - corners[0])
+            .normalize()
+    }
+
+    pub fn face_normals(&self) -> Vec<Vec3> {
+        (0..self.faces.len()).map(|i| self.face_normal(i)).collect()
+    }
+}
+
+pub fn tetrahedron() -> Polyhedron {
+    let vertices = vec![
+        Vec3::new(1.0, 1.0, 1.0),
+        Vec3::new(1.0, -1.0, -1.0),
+        Vec3::new(-1.0, 1.0, -1.0),
+        Vec3::new(-1.0, -1.0, 1.0),
+    ];
+    // Each face is opposite a vertex.
+    let normals: Vec<Vec3> = vertices.iter().map(|v| -*v).collect();
+
+    Polyhedron::from_face_normals(vertices, &normals)
+}
+
+pub fn cube() -> Polyhedron {
+    let vertices = corners(1.0, 1.0, 1.0);
+    let normals = [
+        Vec3::X,
+        Vec3::NEG_X,
+        Vec3::Y,
+        Vec3::NEG_Y,
+        Vec3::Z,
+        Vec3::NEG_Z,
+    ];
+
+    Polyhedron::from_face_normals(vertices, &normals)
+}
+
+pub fn octahedron() -> Polyhedron {
+    let vertices = vec![
+        Vec3::X,
+        Vec3::NEG_X,
+        Vec3::Y,
+        Vec3::NEG_Y,
+        Vec3::Z,
+        Vec3::NEG_Z,
+    ];
+
+    Polyhedron::from_face_normals(vertices, &corners(1.0, 1.0, 1.0))
+}
+
+/// Pentagonal trapezohedron, the shape of a d10.
+pub fn trapezohedron() -> Polyhedron {
+    let height = 1.1;
+    // Ring offset that keeps every kite planar.
+    let cos36 = (TAU / 10.0).cos();
+    let ring = height * (1.0 - cos36) / (1.0 + cos36);
+
+    let top = Vec3::Y * height;
+    let bottom = Vec3::NEG_Y * height;
+    let around: Vec<Vec3> = (0..10)
+        .map(|i| {
+            let angle = i as f32 * TAU / 10.0;
+            let y = if i % 2 == 0 { ring } else { -ring };
+            Vec3::new(angle.cos(), y, angle.sin())
+        })
+        .collect();
+
+    let mut normals = Vec::new();
+    for k in 0..5 {
+        let (a, b, c) = (around[2 * k], around[2 * k + 1], around[(2 * k + 2) % 10]);
+        normals.push(outward((a - top).cross(c - top), a + b + c + top));
+
+        let (a, b, c) = (b, c, around[(2 * k + 3) % 10]);
+        normals.push(outward((a - bottom).cross(c - bottom), a + b + c + bottom));
+    }
+
+    let mut vertices = vec![top, bottom];
+    vertices.extend(around);
+
+    Polyhedron::from_face_normals(vertices, &normals)
+}
+
+pub fn dodecahedron() -> Polyhedron {
+    let mut vertices = corners(1.0, 1.0, 1.0);
+    vertices.extend(cyclic(1.0 / PHI, PHI));
+
+    Polyhedron::from_face_normals(vertices, &cyclic(PHI, 1.0))
+}
+
+pub fn icosahedron() -> Polyhedron {
+    let mut normals = corners(1.0, 1.0, 1.0);
+    normals.extend(cyclic(PHI, 1.0 / PHI));
+
+    Polyhedron::from_face_normals(cyclic(1.0, PHI), &normals)
+}
+
+/// Hands out values so that opposite faces add up to `sides + 1`, like printed dice.
+pub fn opposite_values(normals: &[Vec3], sides: i32) -> Vec<i32> {
+    let mut values = vec![0; normals.len()];
+    let mut next = 1;
+
+    for i in 0..normals.len() {
+        if values[i] != 0 {
+            continue;
+        }
+
+        values[i] = next;
+        if let Some(j) = (0..normals.len()).find(|j| normals[*j].dot(normals[i]) < -0.999) {
+            values[j] = sides + 1 - next;
+        }
+        next += 1;
+    }
+
+    values
+}
+
+/// Flat shaded mesh with each face showing the atlas cell at `cells[face]`.
+pub fn mesh(poly: &Polyhedron, cells: &[usize]) -> Mesh {
+    let mut positions: Vec<[f32; 3]> = Vec::new();
+    let mut normals: Vec<[f32; 3]> = Vec::new();
+    let mut uvs: Vec<[f32; 2]> = Vec::new();
+
+    let cell_size = Vec2::new(1.0 / ATLAS_COLUMNS as f32, 1.0 / ATLAS_ROWS as f32);
+
+    for (i, corners) in poly.faces.iter().enumerate() {
+        let normal = poly.face_normal(i);
+        let centre = corners.iter().sum::<Vec3>() / corners.len() as f32;
+        let radius = corners
+            .iter()
+            .map(|c| c.distance(centre))
+            .fold(0.0, f32::max);
+
+        // Lay the face flat inside its cell, right-way round when seen from outside.
+        let u = (corners[0] - centre).normalize();
+        let w = normal.cross(u);
+        let cell = Vec2::new(
+            (cells[i] % ATLAS_COLUMNS) as f32,
+            (cells[i] / ATLAS_COLUMNS) as f32,
+        );
+        let cell_centre = (cell + 0.5) * cell_size;
+        let uv = |p: Vec3| {
+            let local = Vec2::new((p - centre).dot(u), -(p - centre).dot(w)) / radius;
+            (cell_centre + local * cell_size * 0.48).to_array()
+        };
+
+        for k in 1..corners.len() - 1 {
+            for corner in [corners[0], corners[k], corners[k + 1]] {
+                positions.push(corner.to_array());
+                normals.push(normal.to_array());
+                uvs.push(uv(corner));
+            }
+        }
+    }
+
+    Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    )
+    .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+}
+
+pub fn atlas_cell(label: &str) -> usize {
+    ATLAS_LABELS.iter().position(|l| *l == label).unwrap_or(0)
+}
+
+fn outward(normal: Vec3, towards: Vec3) -> Vec3 {
+    if normal.dot(towards) < 0.0 {
+        -normal
+    } else {
+        normal
+    }
+}
+
+/// All sign combinations of `(x, y, z)`.
+fn corners(x: f32, y: f32, z: f32) -> Vec<Vec3> {
+    let mut out = Vec::new();
+    for sx in [-1.0, 1.0] {
+        for sy in [-1.0, 1.0] {
+            for sz in [-1.0, 1.0] {
+                out.push(Vec3::new(x * sx, y * sy, z * sz));
+            }
+        }
+    }
+    out
+}
+
+/// All sign combinations of the cyclic permutations of `(0, a, b)`.
+fn cyclic(a: f32, b: f32) -> Vec<Vec3> {
+    let mut out = Vec::new();
+    for sa in [-1.0, 1.0] {
+        for sb in [-1.0, 1.0] {
+            out.push(Vec3::new(0.0, a * sa, b * sb));
+            out.push(Vec3::new(a * sa, b * sb, 0.0));
+            out.push(Vec3::new(b * sb, 0.0, a * sa));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::super::{
+        faces::{faces_from_mesh, validate_faces, FaceSidecar},
+        polyhedron, polyhedron_faces, DiceKind,
+    };
+    use super::*;
+
+    #[test]
+    fn generated_dice_read_back_their_faces() {
+        for kind in DiceKind::ALL {
+            let poly = polyhedron(kind).scaled(kind.radius());
+            let faces = polyhedron_faces(kind, &poly);
+            let cells: Vec<usize> = faces
+                .iter()
+                .map(|face| atlas_cell(&kind.label(face.value)))
+                .collect();
+
+            // One atlas unit per cell, so a UV lands on its cell's column and row
+            let tiles: HashMap<(u32, u32), i32> = faces
+                .iter()
+                .zip(cells.iter())
+                .map(|(face, cell)| {
+                    let tile = ((cell % ATLAS_COLUMNS) as u32, (cell / ATLAS_COLUMNS) as u32);
+                    (tile, face.value)
+                })
+                .collect();
+            let sidecar = FaceSidecar {
+                atlas_size: Vec2::new(ATLAS_COLUMNS as f32, ATLAS_ROWS as f32),
+                tile_size: 1.0,
+                tiles,
+            };
+
+            let read = faces_from_mesh(&mesh(&poly, &cells), &sidecar)
+                .unwrap_or_else(|e| panic!("{}: {}", kind.name(), e));
+            validate_faces(kind, &read).unwrap();
+            assert_eq!(read.len(), faces.len(), "{}", kind.name());
+
+            // The mesh faces point outwards, a d4 is read from the face it lands on
+            let outward = if kind == DiceKind::D4 { -1.0 } else { 1.0 };
+            for face in faces.iter() {
+                let printed = read
+                    .iter()
+                    .find(|read| read.normal.dot(face.normal * outward) > 0.999)
+                    .unwrap_or_else(|| panic!("{} lost its {}", kind.name(), face.value));
+                assert_eq!(printed.value, face.value, "{}", kind.name());
+            }
+        }
+    }
+
+    #[test]
+    fn faces_are_planar() {
+        for kind in DiceKind::ALL {
+            let poly = polyhedron(kind);
+
+            for (i, corners) in poly.faces.iter().enumerate() {
+                let normal = poly.face_normal(i);
+                for corner in corners.iter() {
+                    let off_plane = (*corner - corners[0]).dot(normal).abs();
+                    assert!(off_plane < 1e-4, "{} face {} is bent", kind.name(), i);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn d10_faces_are_kites() {
+        let poly = trapezohedron();
+
+        assert_eq!(poly.faces.len(), 10);
+        assert!(poly.faces.iter().all(|corners| corners.len() == 4));
+    }
+}
@@ -0,0 +1,255 @@
+//! Throws thousands of dice headless and checks every face comes up about as often as it
+//! should. Run with `cargo run --release -- --dice-fairness [throws per die] [sigmas]`,
+//! set `SEED` to repeat a run. Exits with an error if any die looks loaded.
+
+use std::{collections::HashMap, time::Duration};
+
+use bevy::{prelude::*, time::TimeUpdateStrategy};
+use bevy_rapier3d::prelude::*;
+use rand::Rng;
+
+use crate::{
+    player::components::Dice,
+    rng::{GameRng, RngStream},
+};
+
+use super::{
+    faces::{faces_from_mesh, validate_faces, FaceSidecar},
+    history::chi_squared_p_value,
+    polyhedron, polyhedron_faces, settle_dice_system, throw_die, DiceFace, DiceKind, DiceSet,
+    DiceSettings, DiceShape, D6_FACES_PATH,
+};
+
+/// Dice in the air at once, laid out in a grid far enough apart not to meet.
+const BATCH_SIDE: u32 = 8;
+const BATCH_SPACING: f32 = 4.0;
+/// Frames to wait for a batch before giving up on the dice still moving.
+const MAX_BATCH_FRAMES: u32 = 60 * 60;
+/// The d6 model the game throws, read from disk since there's no asset server here.
+const D6_MESH_PATH: &str = "assets/meshes/dice.obj";
+
+#[derive(Resource)]
+struct FairnessRun {
+    kinds: Vec<DiceKind>,
+    throws: u32,
+    thrown: u32,
+    batch_frames: u32,
+    results: HashMap<DiceKind, Vec<i32>>,
+    /// Dice that never settled.
+    lost: u32,
+}
+
+/// Throws `throws` of every die and prints the face counts. Returns false if any face
+/// strays more than `sigmas` standard deviations from a fair count.
+pub fn run(throws: u32, sigmas: f32) -> bool {
+    let dice_set = match headless_dice_set() {
+        Ok(dice_set) => dice_set,
+        Err(e) => {
+            println!("Can't build the dice: {}", e);
+            return false;
+        }
+    };
+
+    let mut app = App::new();
+
+    app.add_plugins((
+        MinimalPlugins,
+        TransformPlugin,
+        HierarchyPlugin,
+        AssetPlugin::default(),
+        bevy::scene::ScenePlugin,
+    ));
+    app.init_asset::<Mesh>();
+    app.init_asset::<StandardMaterial>();
+    app.add_plugins(RapierPhysicsPlugin::<NoUserData>::default());
+
+    // One physics step and one fixed slice of time per update, as fast as we can go.
+    app.insert_resource(RapierConfiguration {
+        timestep_mode: TimestepMode::Fixed {
+            dt: 1.0 / 60.0,
+            substeps: 1,
+        },
+        ..RapierConfiguration::new(1.0)
+    });
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(
+        1.0 / 60.0,
+    )));
+
+    let rng = GameRng::from_env();
+    let seed = rng.seed();
+    app.insert_resource(rng);
+    app.insert_resource(dice_set);
+    app.insert_resource(DiceSettings::default());
+    app.insert_resource(FairnessRun {
+        kinds: DiceKind::ALL.to_vec(),
+        throws,
+        thrown: 0,
+        batch_frames: 0,
+        results: HashMap::new(),
+        lost: 0,
+    });
+
+    app.add_systems(Startup, spawn_floor);
+    app.add_systems(Update, (settle_dice_system, batch_system).chain());
+
+    println!(
+        "Throwing {} of each die, seed {}, failing beyond {} sigma",
+        throws, seed, sigmas
+    );
+
+    while !app.world().resource::<FairnessRun>().kinds.is_empty() {
+        app.update();
+    }
+
+    report(app.world().resource::<FairnessRun>(), sigmas)
+}
+
+/// The real colliders and face tables, without meshes or materials to render them. The d6
+/// reads its faces off the shipped model and face file, like the game does.
+fn headless_dice_set() -> Result<DiceSet, String> {
+    let mut dice_set = DiceSet::default();
+
+    for kind in DiceKind::ALL {
+        let poly = polyhedron(kind).scaled(kind.radius());
+        let faces = match kind {
+            DiceKind::D6 => d6_mesh_faces()?,
+            _ => polyhedron_faces(kind, &poly),
+        };
+
+        dice_set.shapes.insert(
+            kind,
+            DiceShape {
+                mesh: Handle::default(),
+                material: Handle::default(),
+                collider: Collider::convex_hull(&poly.vertices).unwrap(),
+                faces,
+                faces_from: None,
+            },
+        );
+    }
+
+    Ok(dice_set)
+}
+
+fn d6_mesh_faces() -> Result<Vec<DiceFace>, String> {
+    let bytes = std::fs::read(D6_MESH_PATH).map_err(|e| format!("{}: {}", D6_MESH_PATH, e))?;
+    let mesh =
+        bevy_obj::load_obj_from_bytes(&bytes).map_err(|e| format!("{}: {}", D6_MESH_PATH, e))?;
+    let sidecar = FaceSidecar::load(D6_FACES_PATH)?;
+
+    let faces = faces_from_mesh(&mesh, &sidecar)?;
+    validate_faces(DiceKind::D6, &faces)?;
+    Ok(faces)
+}
+
+fn spawn_floor(mut commands: Commands) {
+    commands.spawn((
+        TransformBundle::default(),
+        RigidBody::Fixed,
+        Collider::cuboid(200.0, 0.5, 200.0),
+    ));
+}
+
+/// Collects the faces of a settled batch and throws the next one.
+fn batch_system(
+    mut commands: Commands,
+    query: Query<(Entity, &Dice)>,
+    dice_set: Res<DiceSet>,
+    mut run: ResMut<FairnessRun>,
+    mut rng: ResMut<GameRng>,
+) {
+    let Some(kind) = run.kinds.first().copied() else {
+        return;
+    };
+
+    run.batch_frames += 1;
+    let settled = query.iter().all(|(_, dice)| dice.rolled);
+    if !query.is_empty() && !settled && run.batch_frames < MAX_BATCH_FRAMES {
+        return;
+    }
+
+    for (entity, dice) in query.iter() {
+        if dice.rolled {
+            run.results.entry(dice.kind).or_default().push(dice.value);
+        } else {
+            run.lost += 1;
+        }
+        commands.entity(entity).despawn();
+    }
+
+    if run.thrown >= run.throws {
+        run.kinds.remove(0);
+        run.thrown = 0;
+        return;
+    }
+
+    let rng = rng.stream(RngStream::Dice);
+    let batch = (BATCH_SIDE * BATCH_SIDE).min(run.throws - run.thrown);
+
+    for i in 0..batch {
+        let cell = Vec2::new((i % BATCH_SIDE) as f32, (i / BATCH_SIDE) as f32);
+        let position = Vec3::new(cell.x, 0.0, cell.y) * BATCH_SPACING + Vec3::Y * 1.5;
+        let die = throw_die(
+            &mut commands,
+            &dice_set,
+            kind,
+            position,
+            ExternalImpulse::default(),
+            rng,
+        );
+
+        let sideways = crate::mathx::random::vec2(rng).normalize_or_zero();
+        commands.entity(die).insert(Velocity {
+            linvel: Vec3::new(sideways.x, 0.0, sideways.y) * rng.gen_range(1.0..4.0),
+            angvel: crate::mathx::random::vec3(rng) * rng.gen_range(5.0..20.0),
+        });
+    }
+
+    run.thrown += batch;
+    run.batch_frames = 0;
+}
+
+fn report(run: &FairnessRun, sigmas: f32) -> bool {
+    let mut fair = true;
+
+    for kind in DiceKind::ALL {
+        let results = run.results.get(&kind).cloned().unwrap_or_default();
+        let values = kind.values();
+        let rolls = results.len() as f32;
+        let p = 1.0 / values.len() as f32;
+        let expected = rolls * p;
+        let sigma = (rolls * p * (1.0 - p)).sqrt();
+
+        let mut chi_squared = 0.0;
+        let mut worst: f32 = 0.0;
+        let mut counts = Vec::new();
+
+        for value in values.iter() {
+            let count = results.iter().filter(|v| *v == value).count() as f32;
+            chi_squared += (count - expected).powi(2) / expected.max(1.0);
+            worst = worst.max((count - expected).abs() / sigma.max(f32::EPSILON));
+            counts.push(format!("{}:{}", kind.label(*value), count));
+        }
+
+        let p_value = chi_squared_p_value(chi_squared, values.len() as f32 - 1.0);
+        let passed = rolls > 0.0 && worst <= sigmas;
+        fair &= passed;
+
+        println!(
+            "{:>4} {} x{}  {}  chi2 {:.1}, p {:.3}, worst face {:.1} sigma",
+            kind.name(),
+            if passed { "ok  " } else { "FAIL" },
+            results.len(),
+            counts.join(" "),
+            chi_squared,
+            p_value,
+            worst
+        );
+    }
+
+    if run.lost > 0 {
+        println!("{} dice never settled", run.lost);
+    }
+
+    fair
+}
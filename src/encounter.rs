@@ -0,0 +1,607 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use crate::{
+    camera::CameraState,
+    combat::{Armour, DamageEvent, DamageRoll, Dead, DeathEvent, Health},
+    dice::{
+        checks::{Abilities, Ability},
+        notation::{DiceExpr, Op},
+        ExpressionRolledEvent, RollExpressionEvent, RollPurpose,
+    },
+    enemy::Enemy,
+    pickup::INTERACT_DISTANCE,
+    player::components::{Eye, Inventory, Player},
+    rng::{GameRng, RngStream},
+    weapon::{WeaponAttack, WeaponInventory},
+    AddUiMessageEvent,
+};
+
+/// An enemy this close to the player starts an encounter.
+const CONTACT_RANGE: f32 = 2.5;
+/// Enemies this close when it starts join in.
+const ENCOUNTER_RADIUS: f32 = 12.0;
+/// Breather between turns, after the dice camera has finished.
+const TURN_PAUSE: f32 = 0.6;
+/// Armour of anything without an [`Armour`].
+const DEFAULT_ARMOUR: i32 = 10;
+pub(crate) const UNARMED: DamageRoll = DamageRoll::new("1d2");
+const INITIATIVE_ROLL: &str = "1d20";
+
+#[derive(Resource)]
+pub struct EncounterState {
+    /// F3 switches between turn-based encounters and fighting in real time.
+    pub enabled: bool,
+    /// `None` while exploring.
+    pub encounter: Option<Encounter>,
+}
+
+impl Default for EncounterState {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            encounter: None,
+        }
+    }
+}
+
+pub struct Encounter {
+    /// Everyone in the fight, highest initiative first.
+    pub order: Vec<Entity>,
+    pub turn: usize,
+    pub round: u32,
+    pub phase: TurnPhase,
+}
+
+impl Encounter {
+    pub fn current(&self) -> Entity {
+        self.order[self.turn]
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TurnPhase {
+    /// Waits for the dice camera, then `seconds` more before the next turn.
+    Pause { seconds: f32 },
+    /// The player looks at an enemy and clicks to attack it.
+    Choosing,
+    /// To-hit dice in the air.
+    ToHit { target: Entity },
+    /// Damage dice in the air.
+    Damage { target: Entity },
+}
+
+#[derive(Event)]
+pub struct EncounterStartedEvent {
+    pub order: Vec<Entity>,
+}
+
+#[derive(Event)]
+pub struct EncounterEndedEvent {
+    pub player_won: bool,
+}
+
+pub(crate) fn init(app: &mut App) {
+    app.insert_resource(EncounterState::default());
+    app.add_event::<EncounterStartedEvent>();
+    app.add_event::<EncounterEndedEvent>();
+
+    app.add_systems(
+        Update,
+        (
+            start_encounter_system,
+            encounter_turn_system,
+            encounter_roll_listener,
+        )
+            .chain()
+            .after(crate::combat::projectile_system),
+    );
+}
+
+/// Run condition for real-time systems that stop during an encounter.
+pub fn exploring(state: Res<EncounterState>) -> bool {
+    state.encounter.is_none()
+}
+
+fn start_encounter_system(
+    mut state: ResMut<EncounterState>,
+    player_query: Query<(Entity, &Transform, &Player, Option<&Abilities>)>,
+    enemy_query: Query<(Entity, &Enemy, &Transform, Option<&Abilities>), Without<Dead>>,
+    key: Res<ButtonInput<KeyCode>>,
+    mut rng: ResMut<GameRng>,
+    mut started_events: EventWriter<EncounterStartedEvent>,
+    mut add_message_event: EventWriter<AddUiMessageEvent>,
+) {
+    if key.just_pressed(KeyCode::F3) {
+        state.enabled = !state.enabled;
+        add_message_event.send(AddUiMessageEvent {
+            message: format!(
+                "Turn-based encounters {}",
+                if state.enabled { "on" } else { "off" }
+            ),
+            duration: 1.5,
+        });
+    }
+
+    if !state.enabled || state.encounter.is_some() {
+        return;
+    }
+
+    // Not while the player's own dice are still rolling
+    let Ok((player_entity, player_xform, player, player_abilities)) = player_query.get_single()
+    else {
+        return;
+    };
+    if player.dice_active {
+        return;
+    }
+
+    let position = player_xform.translation;
+    let contact = enemy_query
+        .iter()
+        .any(|(_, _, xform, _)| xform.translation.distance(position) <= CONTACT_RANGE);
+    if !contact {
+        return;
+    }
+
+    let rng = rng.stream(RngStream::Combat);
+    let initiative = DiceExpr::parse(INITIATIVE_ROLL).unwrap();
+    let dexterity = |abilities: Option<&Abilities>| {
+        abilities.map_or(0, |abilities| abilities.modifier(Ability::Dexterity))
+    };
+
+    // The player is listed first, so they go first on a tie
+    let mut rolls = vec![(
+        (player_entity, String::from("You")),
+        initiative.roll(rng).total + dexterity(player_abilities),
+    )];
+    for (entity, enemy, xform, abilities) in enemy_query.iter() {
+        if xform.translation.distance(position) <= ENCOUNTER_RADIUS {
+            rolls.push((
+                (entity, format!("{:?}", enemy.kind)),
+                initiative.roll(rng).total + dexterity(abilities),
+            ));
+        }
+    }
+    initiative_order(&mut rolls);
+
+    let order: Vec<Entity> = rolls.iter().map(|((entity, _), _)| *entity).collect();
+    let listing: Vec<String> = rolls
+        .iter()
+        .map(|((_, name), roll)| format!("{} {}", name, roll))
+        .collect();
+
+    add_message_event.send(AddUiMessageEvent {
+        message: format!("Encounter! Initiative: {}", listing.join(", ")),
+        duration: 4.0,
+    });
+
+    state.encounter = Some(Encounter {
+        // The first pause moves on to the top of the order
+        turn: order.len() - 1,
+        round: 0,
+        phase: TurnPhase::Pause { seconds: 1.0 },
+        order: order.clone(),
+    });
+    started_events.send(EncounterStartedEvent { order });
+}
+
+/// Hands out turns and throws the dice for each attack.
+fn encounter_turn_system(
+    mut state: ResMut<EncounterState>,
+    mut combatant_query: Query<(
+        &Health,
+        Option<&Enemy>,
+        Option<&Abilities>,
+        Option<&WeaponInventory>,
+        Option<&mut Inventory>,
+    )>,
+    player_query: Query<(Entity, &Eye), With<Player>>,
+    rapier_context: Res<RapierContext>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    camera_state: Res<CameraState>,
+    time: Res<Time>,
+    mut death_events: EventReader<DeathEvent>,
+    mut roll_events: EventWriter<RollExpressionEvent>,
+    mut ended_events: EventWriter<EncounterEndedEvent>,
+    mut add_message_event: EventWriter<AddUiMessageEvent>,
+) {
+    let Some(encounter) = state.encounter.as_mut() else {
+        death_events.clear();
+        return;
+    };
+    let Ok((player_entity, eye)) = player_query.get_single() else {
+        return;
+    };
+
+    let alive = |entity: Entity| {
+        combatant_query
+            .get(entity)
+            .map_or(false, |(health, ..)| !health.is_dead())
+    };
+
+    // The player respawns on death, so their death has to be caught as it happens
+    let player_died = death_events
+        .read()
+        .filter(|ev| ev.entity == player_entity)
+        .count()
+        > 0;
+    let enemies_left = encounter
+        .order
+        .iter()
+        .any(|entity| *entity != player_entity && alive(*entity));
+
+    if player_died || !enemies_left {
+        let message = if player_died {
+            "You were defeated."
+        } else {
+            "The fight is over."
+        };
+        add_message_event.send(AddUiMessageEvent {
+            message: String::from(message),
+            duration: 3.0,
+        });
+        ended_events.send(EncounterEndedEvent {
+            player_won: !player_died,
+        });
+        state.encounter = None;
+        return;
+    }
+
+    match encounter.phase {
+        TurnPhase::Pause { seconds } => {
+            if camera_state.scene_params.is_some() {
+                return;
+            }
+            let seconds = seconds - time.delta_seconds();
+            encounter.phase = TurnPhase::Pause { seconds };
+            if seconds > 0.0 {
+                return;
+            }
+
+            // Skip anyone who died since their last turn
+            loop {
+                encounter.turn = (encounter.turn + 1) % encounter.order.len();
+                if encounter.turn == 0 {
+                    encounter.round += 1;
+                }
+                if alive(encounter.current()) {
+                    break;
+                }
+            }
+
+            let current = encounter.current();
+            if current == player_entity {
+                encounter.phase = TurnPhase::Choosing;
+                add_message_event.send(AddUiMessageEvent {
+                    message: format!(
+                        "Round {}: your turn, look at an enemy and click to attack",
+                        encounter.round
+                    ),
+                    duration: 3.0,
+                });
+            } else {
+                encounter.phase = TurnPhase::ToHit {
+                    target: player_entity,
+                };
+            }
+        }
+        TurnPhase::Choosing => {
+            if !mouse.just_pressed(MouseButton::Left) {
+                return;
+            }
+
+            let filter = QueryFilter::default()
+                .exclude_collider(player_entity)
+                .exclude_sensors();
+            let target = rapier_context
+                .cast_ray(
+                    eye.position,
+                    *eye.forward(),
+                    INTERACT_DISTANCE * 4.0,
+                    true,
+                    filter,
+                )
+                .map(|(entity, _)| entity)
+                .filter(|entity| {
+                    encounter.order.contains(entity) && *entity != player_entity && alive(*entity)
+                });
+
+            match target {
+                Some(target) => encounter.phase = TurnPhase::ToHit { target },
+                None => {
+                    add_message_event.send(AddUiMessageEvent {
+                        message: String::from("Look at an enemy to attack it"),
+                        duration: 1.5,
+                    });
+                    return;
+                }
+            }
+        }
+        _ => return,
+    }
+
+    // Both ways into `ToHit` end up here to throw the to-hit dice
+    let attacker = encounter.current();
+    let Ok((_, enemy, abilities, weapons, inventory)) = combatant_query.get_mut(attacker) else {
+        return;
+    };
+    let (to_hit, _) = attack_of(enemy, abilities, weapons);
+    let expression = DiceExpr::parse(&format!("1d20{:+}", to_hit)).unwrap();
+
+    roll_events.send(RollExpressionEvent {
+        roller: attacker,
+        physical: use_dice(inventory, &expression),
+        expression,
+        purpose: RollPurpose::Attack,
+        target: None,
+        show: true,
+        throw: None,
+        item: None,
+    });
+}
+
+/// Reads the to-hit and damage dice of whoever's turn it is.
+fn encounter_roll_listener(
+    mut state: ResMut<EncounterState>,
+    mut combatant_query: Query<(
+        Option<&Enemy>,
+        Option<&Abilities>,
+        Option<&WeaponInventory>,
+        Option<&mut Inventory>,
+    )>,
+    armour_query: Query<&Armour>,
+    player_query: Query<(), With<Player>>,
+    mut rolled_events: EventReader<ExpressionRolledEvent>,
+    mut roll_events: EventWriter<RollExpressionEvent>,
+    mut damage_events: EventWriter<DamageEvent>,
+    mut add_message_event: EventWriter<AddUiMessageEvent>,
+) {
+    for ev in rolled_events.read() {
+        let Some(encounter) = state.encounter.as_mut() else {
+            return;
+        };
+        if ev.roller != encounter.current() {
+            continue;
+        }
+
+        let name = |entity: Entity| match combatant_query.get(entity) {
+            Ok((Some(enemy), ..)) => format!("the {:?}", enemy.kind),
+            _ if player_query.contains(entity) => String::from("you"),
+            _ => String::from("something"),
+        };
+
+        match (encounter.phase, &ev.purpose) {
+            (TurnPhase::ToHit { target }, RollPurpose::Attack) => {
+                let armour = armour_query.get(target).map_or(DEFAULT_ARMOUR, |a| a.0);
+                let natural = ev
+                    .result
+                    .dice
+                    .iter()
+                    .find(|die| die.kept && die.sides == 20)
+                    .map_or(0, |die| die.value);
+
+                let outcome = AttackOutcome::from_roll(natural, ev.result.total, armour);
+                add_message_event.send(AddUiMessageEvent {
+                    message: format!(
+                        "{} rolls {} to hit {} (armour {}): {}",
+                        capitalise(&name(ev.roller)),
+                        ev.result.total,
+                        name(target),
+                        armour,
+                        outcome.verdict()
+                    ),
+                    duration: 3.0,
+                });
+
+                if outcome == AttackOutcome::Miss {
+                    encounter.phase = TurnPhase::Pause {
+                        seconds: TURN_PAUSE,
+                    };
+                    continue;
+                }
+
+                let Ok((enemy, abilities, weapons, inventory)) = combatant_query.get_mut(ev.roller)
+                else {
+                    continue;
+                };
+                let (_, damage) = attack_of(enemy, abilities, weapons);
+                let expression =
+                    damage_expression(damage.expr(), outcome == AttackOutcome::Critical);
+
+                encounter.phase = TurnPhase::Damage { target };
+                roll_events.send(RollExpressionEvent {
+                    roller: ev.roller,
+                    physical: use_dice(inventory, &expression),
+                    expression,
+                    purpose: RollPurpose::Damage,
+                    target: None,
+                    show: true,
+                    throw: None,
+                    item: None,
+                });
+            }
+            (TurnPhase::Damage { target }, RollPurpose::Damage) => {
+                let amount = ev.result.total.max(0);
+                damage_events.send(DamageEvent {
+                    target,
+                    amount,
+                    source: Some(ev.roller),
+                });
+                add_message_event.send(AddUiMessageEvent {
+                    message: format!(
+                        "{} deals {} damage to {}",
+                        capitalise(&name(ev.roller)),
+                        amount,
+                        name(target)
+                    ),
+                    duration: 3.0,
+                });
+
+                encounter.phase = TurnPhase::Pause {
+                    seconds: TURN_PAUSE,
+                };
+            }
+            _ => {}
+        }
+    }
+}
+
+/// How a to-hit roll went.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AttackOutcome {
+    Miss,
+    Hit,
+    Critical,
+}
+
+impl AttackOutcome {
+    /// A natural 20 always hits and a natural 1 always misses, otherwise the `total` has to
+    /// reach the target's `armour`.
+    pub fn from_roll(natural: i32, total: i32, armour: i32) -> AttackOutcome {
+        match natural {
+            20 => AttackOutcome::Critical,
+            1 => AttackOutcome::Miss,
+            _ if total >= armour => AttackOutcome::Hit,
+            _ => AttackOutcome::Miss,
+        }
+    }
+
+    pub fn verdict(&self) -> &'static str {
+        match self {
+            AttackOutcome::Miss => "miss",
+            AttackOutcome::Hit => "hit",
+            AttackOutcome::Critical => "critical hit!",
+        }
+    }
+}
+
+/// The damage dice to throw, twice over on a critical.
+pub fn damage_expression(damage: DiceExpr, critical: bool) -> DiceExpr {
+    if !critical {
+        return damage;
+    }
+
+    DiceExpr::Binary(Box::new(damage.clone()), Op::Add, Box::new(damage))
+}
+
+/// Sorts initiative rolls highest first. Stable, so whoever is listed first wins a tie.
+fn initiative_order<T>(rolls: &mut [(T, i32)]) {
+    rolls.sort_by(|(_, a), (_, b)| b.cmp(a));
+}
+
+/// To-hit bonus and damage dice: an enemy's from its def, the player's from their
+/// equipped weapon and the ability it leans on.
+fn attack_of(
+    enemy: Option<&Enemy>,
+    abilities: Option<&Abilities>,
+    weapons: Option<&WeaponInventory>,
+) -> (i32, DamageRoll) {
+    if let Some(enemy) = enemy {
+        let def = enemy.kind.def();
+        return (def.to_hit, def.attack.damage);
+    }
+
+    let Some(def) = weapons
+        .and_then(|weapons| weapons.current())
+        .map(|kind| kind.def())
+    else {
+        return (0, UNARMED);
+    };
+    let ability = match def.attack {
+        WeaponAttack::Melee { .. } => Ability::Strength,
+        WeaponAttack::Ranged { .. } => Ability::Dexterity,
+    };
+    let bonus = abilities.map_or(0, |abilities| abilities.modifier(ability));
+
+    (bonus, def.damage)
+}
+
+/// Takes the dice for a roll out of the roller's inventory. Without enough of them the
+/// roll comes from the RNG instead. Enemies bring their own.
+fn use_dice(inventory: Option<Mut<Inventory>>, expression: &DiceExpr) -> bool {
+    let needed = expression.dice().len() as u32;
+
+    match inventory {
+        Some(mut inventory) if inventory.dice >= needed => {
+            inventory.dice -= needed;
+            true
+        }
+        Some(_) => false,
+        None => true,
+    }
+}
+
+fn capitalise(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use crate::dice::notation::DieSource;
+
+    use super::*;
+
+    /// Hands out the given faces in order, whatever the sides.
+    struct Faces(VecDeque<i32>);
+
+    impl DieSource for Faces {
+        fn roll(&mut self, _sides: u32) -> Option<i32> {
+            self.0.pop_front()
+        }
+    }
+
+    #[test]
+    fn naturals_beat_armour() {
+        assert_eq!(
+            AttackOutcome::from_roll(20, 15, 30),
+            AttackOutcome::Critical
+        );
+        assert_eq!(AttackOutcome::from_roll(1, 25, 10), AttackOutcome::Miss);
+    }
+
+    #[test]
+    fn total_has_to_reach_armour() {
+        assert_eq!(AttackOutcome::from_roll(12, 14, 14), AttackOutcome::Hit);
+        assert_eq!(AttackOutcome::from_roll(12, 15, 14), AttackOutcome::Hit);
+        assert_eq!(AttackOutcome::from_roll(12, 13, 14), AttackOutcome::Miss);
+        // A 19 is no critical, even when it hits
+        assert_eq!(AttackOutcome::from_roll(19, 22, 10), AttackOutcome::Hit);
+    }
+
+    #[test]
+    fn criticals_double_the_dice() {
+        let damage = DiceExpr::parse("1d6+2").unwrap();
+
+        let normal = damage_expression(damage.clone(), false);
+        assert_eq!(normal.dice().len(), 1);
+
+        let critical = damage_expression(damage, true);
+        assert_eq!(critical.dice().len(), 2);
+        let mut faces = Faces(VecDeque::from([3, 5]));
+        assert_eq!(critical.evaluate(&mut faces).unwrap().total, 12);
+    }
+
+    #[test]
+    fn initiative_goes_highest_first() {
+        let mut rolls = vec![("you", 12), ("goblin", 17), ("rat", 3), ("orc", 12)];
+        initiative_order(&mut rolls);
+
+        let order: Vec<&str> = rolls.iter().map(|(name, _)| *name).collect();
+        // The player was listed first, so they keep the tie with the orc
+        assert_eq!(order, vec!["goblin", "you", "orc", "rat"]);
+    }
+
+    #[test]
+    fn unarmed_is_the_fallback_attack() {
+        let (bonus, damage) = attack_of(None, None, None);
+        assert_eq!(bonus, 0);
+        assert_eq!(damage.notation, UNARMED.notation);
+    }
+}
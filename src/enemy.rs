@@ -1,6 +1,21 @@
 use rand::prelude::*;
 
-use crate::{sprite::CreateSprite3dEvent, GameResourceHandles};
+pub mod attack;
+pub mod steering;
+
+use crate::{
+    combat::{Armour, DamageRoll, Health},
+    dice::items::DiceItem,
+    encounter::exploring,
+    pickup::{LootEntry, LootTable, PickupKind},
+    player::components::Player,
+    rng::{GameRng, RngStream},
+    spawner::{EnemySpawner, SpawnedBy},
+    sprite::CreateSprite3dEvent,
+    GameResourceHandles,
+};
+
+use self::attack::*;
 
 use bevy::{
     app::AppExit,
@@ -11,41 +26,233 @@ use bevy::{
     prelude::*,
 };
 use bevy_rapier3d::{
-    control::KinematicCharacterController,
+    control::{CharacterLength, KinematicCharacterController, KinematicCharacterControllerOutput},
     dynamics::{AdditionalMassProperties, ExternalImpulse, RigidBody, Sleeping, Velocity},
     geometry::Collider,
     na::Dynamic,
-    pipeline::CollisionEvent,
+    pipeline::{CollisionEvent, QueryFilter},
+    plugin::{PhysicsSet, RapierContext},
     rapier::dynamics::BodyPair,
 };
 use bevy_sprite3d::{Sprite3d, Sprite3dBundle};
 
-#[derive(Eq, PartialEq, Hash, Clone)]
+#[derive(Eq, PartialEq, Hash, Clone, Copy, Debug)]
 pub enum EnemyKind {
     Skull,
+    Demon,
+    Ninja,
+    Jack,
+    Agent,
+}
+
+pub struct EnemyDef {
+    pub max_health: i32,
+    /// Top speed in m/s.
+    pub move_speed: f32,
+    /// To-hit rolls have to reach this in an encounter.
+    pub armour: i32,
+    /// Added to the enemy's own to-hit rolls.
+    pub to_hit: i32,
+    pub attack: EnemyAttackDef,
+}
+
+impl EnemyKind {
+    pub const ALL: [EnemyKind; 5] = [
+        EnemyKind::Skull,
+        EnemyKind::Demon,
+        EnemyKind::Ninja,
+        EnemyKind::Jack,
+        EnemyKind::Agent,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "skull" => Some(EnemyKind::Skull),
+            "demon" => Some(EnemyKind::Demon),
+            "ninja" => Some(EnemyKind::Ninja),
+            "jack" => Some(EnemyKind::Jack),
+            "agent" => Some(EnemyKind::Agent),
+            _ => None,
+        }
+    }
+
+    pub fn loot_table(&self) -> LootTable {
+        let entry = |kind: Option<PickupKind>, weight: u32| LootEntry { kind, weight };
+
+        match self {
+            EnemyKind::Skull | EnemyKind::Ninja => LootTable {
+                rolls: 1,
+                entries: vec![
+                    entry(None, 4),
+                    entry(Some(PickupKind::Gold(3)), 4),
+                    entry(Some(PickupKind::Health(4)), 2),
+                ],
+            },
+            EnemyKind::Demon | EnemyKind::Jack => LootTable {
+                rolls: 2,
+                entries: vec![
+                    entry(None, 3),
+                    entry(Some(PickupKind::Gold(8)), 4),
+                    entry(Some(PickupKind::Health(6)), 2),
+                    entry(Some(PickupKind::Dice(1)), 1),
+                    entry(Some(PickupKind::DiceItem(DiceItem::LoadedD6)), 1),
+                ],
+            },
+            EnemyKind::Agent => LootTable {
+                rolls: 1,
+                entries: vec![
+                    entry(None, 2),
+                    entry(Some(PickupKind::Gold(15)), 3),
+                    entry(Some(PickupKind::Item(String::from("a bent badge"))), 1),
+                    entry(Some(PickupKind::DiceItem(DiceItem::LuckyD20)), 1),
+                ],
+            },
+        }
+    }
+
+    pub fn def(&self) -> EnemyDef {
+        match self {
+            // Floating skull that spits slow homing fireballs.
+            EnemyKind::Skull => EnemyDef {
+                max_health: 8,
+                move_speed: 3.0,
+                armour: 11,
+                to_hit: 3,
+                attack: EnemyAttackDef {
+                    attack: EnemyAttack::Ranged {
+                        range: 12.0,
+                        projectile: ProjectileDef {
+                            speed: 5.0,
+                            radius: 0.15,
+                            gravity: 0.0,
+                            homing: 1.5,
+                        },
+                    },
+                    wind_up: 0.6,
+                    recover: 0.4,
+                    cooldown: 2.5,
+                    damage: DamageRoll::new("1d4"),
+                },
+            },
+            EnemyKind::Demon => EnemyDef {
+                max_health: 14,
+                move_speed: 4.0,
+                armour: 13,
+                to_hit: 4,
+                attack: EnemyAttackDef {
+                    attack: EnemyAttack::Melee {
+                        reach: 2.4,
+                        lunge_speed: 7.0,
+                        hit_window: 0.25,
+                    },
+                    wind_up: 0.5,
+                    recover: 0.6,
+                    cooldown: 1.0,
+                    damage: DamageRoll::new("1d6+1"),
+                },
+            },
+            EnemyKind::Ninja => EnemyDef {
+                max_health: 6,
+                move_speed: 6.0,
+                armour: 14,
+                to_hit: 5,
+                attack: EnemyAttackDef {
+                    attack: EnemyAttack::Melee {
+                        reach: 2.2,
+                        lunge_speed: 9.0,
+                        hit_window: 0.2,
+                    },
+                    wind_up: 0.25,
+                    recover: 0.3,
+                    cooldown: 0.6,
+                    damage: DamageRoll::new("1d4+1"),
+                },
+            },
+            // Lobs pumpkins in an arc.
+            EnemyKind::Jack => EnemyDef {
+                max_health: 10,
+                move_speed: 2.5,
+                armour: 12,
+                to_hit: 3,
+                attack: EnemyAttackDef {
+                    attack: EnemyAttack::Ranged {
+                        range: 10.0,
+                        projectile: ProjectileDef {
+                            speed: 9.0,
+                            radius: 0.2,
+                            gravity: 1.0,
+                            homing: 0.0,
+                        },
+                    },
+                    wind_up: 0.8,
+                    recover: 0.5,
+                    cooldown: 2.0,
+                    damage: DamageRoll::new("1d6"),
+                },
+            },
+            EnemyKind::Agent => EnemyDef {
+                max_health: 10,
+                move_speed: 3.5,
+                armour: 13,
+                to_hit: 4,
+                attack: EnemyAttackDef {
+                    attack: EnemyAttack::Ranged {
+                        range: 16.0,
+                        projectile: ProjectileDef {
+                            speed: 14.0,
+                            radius: 0.08,
+                            gravity: 0.0,
+                            homing: 0.0,
+                        },
+                    },
+                    wind_up: 0.4,
+                    recover: 0.3,
+                    cooldown: 1.5,
+                    damage: DamageRoll::new("1d8"),
+                },
+            },
+        }
+    }
 }
 
 #[derive(Component)]
 pub struct Enemy {
-    kind: EnemyKind,
+    pub kind: EnemyKind,
 }
 
+/// Enemies notice the player inside this distance and start closing in.
+const AGGRO_RANGE: f32 = 18.0;
+
 #[derive(Component, Default)]
 pub struct EnemyMotor {
     pub move_dir: Vec3,
     pub time_since_chose_direction: f32,
+    /// Smoothed horizontal velocity, in m/s.
+    pub velocity: Vec3,
+    pub fall_speed: f32,
+    /// Set while attacking, stops the wander behaviour.
+    pub halted: bool,
+    /// Velocity driven by a melee lunge, applied while halted.
+    pub lunge: Vec3,
 }
 
 #[derive(Event)]
 pub struct SpawnEnemyEvent {
     pub position: Vec3,
     pub kind: EnemyKind,
+    pub spawner: Option<Entity>,
 }
 
 pub(crate) fn init(mut app: &mut App) {
     app.add_event::<SpawnEnemyEvent>();
     app.add_systems(FixedFirst, create_enemy_listener);
-    app.add_systems(FixedUpdate, enemy_motor);
+    app.add_systems(
+        FixedUpdate,
+        (enemy_attack_system, enemy_motor)
+            .chain()
+            .run_if(exploring)
+            .before(PhysicsSet::SyncBackend),
+    );
 }
 
 fn create_enemy_listener(
@@ -53,6 +260,7 @@ fn create_enemy_listener(
     resources: Res<GameResourceHandles>,
     mut spawn_events: EventReader<SpawnEnemyEvent>,
     mut event_bus: EventWriter<CreateSprite3dEvent>,
+    mut spawner_query: Query<&mut EnemySpawner>,
 ) {
     for ev in spawn_events.read() {
         let pos = ev.position;
@@ -65,15 +273,25 @@ fn create_enemy_listener(
             .insert(bevy_rapier3d::control::KinematicCharacterController {
                 apply_impulse_to_dynamic_bodies: true,
                 custom_mass: Some(1.0),
+                snap_to_ground: Some(CharacterLength::Absolute(0.5)),
                 ..default()
             })
-            .insert(Enemy {
-                kind: ev.kind.clone(),
-            })
+            .insert(Enemy { kind: ev.kind })
             .insert(EnemyMotor { ..default() })
+            .insert(EnemyAttackState::default())
+            .insert(Health::new(ev.kind.def().max_health))
+            .insert(Armour(ev.kind.def().armour))
             .insert(Collider::capsule_y(0.6, 1.5))
             .id();
 
+        if let Some(spawner) = ev.spawner {
+            commands.entity(enemy).insert(SpawnedBy(spawner));
+
+            if let Ok(mut spawner) = spawner_query.get_mut(spawner) {
+                spawner.pending = spawner.pending.saturating_sub(1);
+            }
+        }
+
         println!("Spawned lil bro at: {:?}", pos);
         event_bus.send(CreateSprite3dEvent {
             entity: enemy,
@@ -86,35 +304,93 @@ fn create_enemy_listener(
 fn enemy_motor(
     mut query: Query<
         (
+            Entity,
+            &Enemy,
             &Transform,
             &mut EnemyMotor,
             &mut KinematicCharacterController,
+            Option<&KinematicCharacterControllerOutput>,
         ),
         With<Enemy>,
     >,
+    player_query: Query<(Entity, &Transform), (With<Player>, Without<Enemy>)>,
+    rapier_context: Res<RapierContext>,
     time: Res<Time>,
+    mut rng: ResMut<GameRng>,
     mut gizmos: Gizmos,
 ) {
     let dt = time.delta_seconds();
+    let player = player_query.get_single().ok();
 
-    for (xform, mut motor, mut controller) in query.iter_mut() {
-        if motor.time_since_chose_direction >= 3.0 {
-            let mut dir = crate::mathx::random::vec3();
-            dir.y = xform.translation.y;
+    let neighbours: Vec<(Entity, Vec3)> = query
+        .iter()
+        .map(|(entity, _, xform, ..)| (entity, xform.translation))
+        .collect();
+
+    // Walls only, other enemies and the player are handled by separation/attacks.
+    let is_wall = |entity: Entity| {
+        !player.map_or(false, |(player_entity, _)| player_entity == entity)
+            && !neighbours.iter().any(|(other, _)| *other == entity)
+    };
+    let filter = QueryFilter::exclude_dynamic()
+        .exclude_sensors()
+        .predicate(&is_wall);
 
-            motor.move_dir = dir * 3.0;
+    for (entity, enemy, xform, mut motor, mut controller, output) in query.iter_mut() {
+        let def = enemy.kind.def();
+        let position = xform.translation;
+
+        if motor.time_since_chose_direction >= 3.0 {
+            let dir = crate::mathx::random::vec2(rng.stream(RngStream::Ai)).normalize_or_zero();
+            motor.move_dir = vec3(dir.x, 0.0, dir.y);
             motor.time_since_chose_direction = 0.0;
         }
-
         motor.time_since_chose_direction += dt;
-        let mut velocity = motor.move_dir * 2.0 * dt;
-        velocity += Dir3::NEG_Y * crate::mathx::GRAVITY * dt;
+
+        let chase_target = player
+            .map(|(_, player_xform)| player_xform.translation)
+            .filter(|target| steering::flat(*target - position).length() <= AGGRO_RANGE);
+
+        let mut desired = match chase_target {
+            Some(target) => steering::arrive(
+                position,
+                target,
+                def.attack.trigger_range() * 0.6,
+                def.move_speed,
+            ),
+            None => motor.move_dir * def.move_speed * 0.4,
+        };
+
+        desired += steering::separate(entity, position, &neighbours, def.move_speed);
+        desired = desired.clamp_length_max(def.move_speed);
+
+        let (avoided, blocked) = steering::avoid_walls(&rapier_context, position, desired, filter);
+        desired = avoided;
+
+        // Wandered into a wall, pick a new direction next tick.
+        if blocked && chase_target.is_none() {
+            motor.time_since_chose_direction = 3.0;
+        }
+
+        let max_delta = steering::MAX_ACCELERATION * dt;
+        let delta = desired - motor.velocity;
+        motor.velocity += delta.clamp_length_max(max_delta);
+
+        if motor.halted {
+            motor.velocity = motor.lunge;
+        }
+
+        // Fall until grounded, then let snap_to_ground keep us there.
+        let grounded = output.map_or(false, |output| output.grounded);
+        motor.fall_speed = if grounded {
+            crate::mathx::GRAVITY * dt
+        } else {
+            motor.fall_speed + crate::mathx::GRAVITY * dt
+        };
+
+        let velocity = motor.velocity * dt + Vec3::NEG_Y * motor.fall_speed * dt;
         controller.translation = Some(velocity);
 
-        gizmos.line(
-            xform.translation,
-            xform.translation + velocity * 5.0,
-            Color::WHITE,
-        );
+        gizmos.line(position, position + motor.velocity, Color::WHITE);
     }
 }
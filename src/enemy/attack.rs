@@ -0,0 +1,233 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use crate::{
+    combat::{spawn_projectile, DamageEvent, DamageRoll, Homing},
+    player::components::Player,
+    rng::{GameRng, RngStream},
+    GameResourceHandles,
+};
+
+use super::{Enemy, EnemyMotor};
+
+#[derive(Clone, Copy, Debug)]
+pub struct ProjectileDef {
+    pub speed: f32,
+    pub radius: f32,
+    /// Multiplier on Rapier's gravity, 0.0 flies straight.
+    pub gravity: f32,
+    /// Turn rate in radians per second towards the target, 0.0 disables homing.
+    pub homing: f32,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum EnemyAttack {
+    /// Winds up, then lunges at the player. Damage lands once if the player is within
+    /// `reach` (centre to centre) at any point during the `hit_window`.
+    Melee {
+        reach: f32,
+        lunge_speed: f32,
+        hit_window: f32,
+    },
+    /// Winds up, then fires a projectile at the player.
+    Ranged {
+        range: f32,
+        projectile: ProjectileDef,
+    },
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct EnemyAttackDef {
+    pub attack: EnemyAttack,
+    pub wind_up: f32,
+    pub recover: f32,
+    pub cooldown: f32,
+    pub damage: DamageRoll,
+}
+
+impl EnemyAttackDef {
+    /// Distance at which the enemy starts winding up an attack.
+    pub fn trigger_range(&self) -> f32 {
+        match self.attack {
+            EnemyAttack::Melee { reach, .. } => reach * 1.5,
+            EnemyAttack::Ranged { range, .. } => range,
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum AttackPhase {
+    Idle,
+    WindUp,
+    Active,
+    Recover,
+}
+
+#[derive(Component)]
+pub struct EnemyAttackState {
+    pub phase: AttackPhase,
+    pub phase_time: f32,
+    pub cooldown: f32,
+    pub hit_landed: bool,
+    /// Direction locked in at the end of the wind-up.
+    pub aim: Vec3,
+}
+
+impl Default for EnemyAttackState {
+    fn default() -> Self {
+        Self {
+            phase: AttackPhase::Idle,
+            phase_time: 0.0,
+            cooldown: 0.0,
+            hit_landed: false,
+            aim: Vec3::ZERO,
+        }
+    }
+}
+
+impl EnemyAttackState {
+    fn enter(&mut self, phase: AttackPhase) {
+        self.phase = phase;
+        self.phase_time = 0.0;
+    }
+
+    pub fn is_busy(&self) -> bool {
+        self.phase != AttackPhase::Idle
+    }
+}
+
+pub(crate) fn enemy_attack_system(
+    mut commands: Commands,
+    mut query: Query<(
+        Entity,
+        &Enemy,
+        &Transform,
+        &mut EnemyAttackState,
+        &mut EnemyMotor,
+    )>,
+    player_query: Query<(Entity, &Transform), (With<Player>, Without<Enemy>)>,
+    rapier_context: Res<RapierContext>,
+    resources: Res<GameResourceHandles>,
+    time: Res<Time>,
+    mut rng: ResMut<GameRng>,
+    mut damage_events: EventWriter<DamageEvent>,
+) {
+    if player_query.is_empty() {
+        return;
+    }
+
+    let dt = time.delta_seconds();
+    let (player_entity, player_xform) = player_query.single();
+    let player_pos = player_xform.translation;
+
+    for (entity, enemy, xform, mut state, mut motor) in query.iter_mut() {
+        let def = enemy.kind.def().attack;
+        let to_player = player_pos - xform.translation;
+        let distance = to_player.length();
+
+        state.cooldown = (state.cooldown - dt).max(0.0);
+        state.phase_time += dt;
+        motor.lunge = Vec3::ZERO;
+
+        match state.phase {
+            AttackPhase::Idle => {
+                motor.halted = false;
+
+                if state.cooldown > 0.0 || distance > def.trigger_range() {
+                    continue;
+                }
+
+                // Only attack what we can actually see.
+                let filter = QueryFilter::default()
+                    .exclude_collider(entity)
+                    .exclude_sensors();
+                let in_sight = rapier_context
+                    .cast_ray(
+                        xform.translation,
+                        to_player / distance,
+                        distance,
+                        true,
+                        filter,
+                    )
+                    .map_or(false, |(hit, _)| hit == player_entity);
+
+                if in_sight {
+                    state.enter(AttackPhase::WindUp);
+                    motor.halted = true;
+                }
+            }
+            AttackPhase::WindUp => {
+                if state.phase_time < def.wind_up {
+                    continue;
+                }
+
+                state.aim = Vec3::new(to_player.x, 0.0, to_player.z).normalize_or_zero();
+                state.hit_landed = false;
+                state.enter(AttackPhase::Active);
+
+                if let EnemyAttack::Ranged { projectile, .. } = def.attack {
+                    let muzzle = xform.translation + state.aim * 0.8;
+                    let target = player_pos - muzzle;
+                    let mut velocity = target.normalize_or_zero() * projectile.speed;
+
+                    // Lob arcing projectiles so they land roughly on the player.
+                    if projectile.gravity > 0.0 {
+                        let flight_time = target.length() / projectile.speed;
+                        velocity.y +=
+                            0.5 * crate::mathx::GRAVITY * projectile.gravity * flight_time;
+                    }
+
+                    let shot = spawn_projectile(
+                        &mut commands,
+                        &resources,
+                        entity,
+                        def.damage,
+                        muzzle,
+                        velocity,
+                        projectile.radius,
+                    );
+
+                    commands
+                        .entity(shot)
+                        .insert(GravityScale(projectile.gravity));
+
+                    if projectile.homing > 0.0 {
+                        commands.entity(shot).insert(Homing {
+                            target: player_entity,
+                            turn_rate: projectile.homing,
+                        });
+                    }
+                }
+            }
+            AttackPhase::Active => match def.attack {
+                EnemyAttack::Melee {
+                    reach,
+                    lunge_speed,
+                    hit_window,
+                } => {
+                    motor.lunge = state.aim * lunge_speed;
+
+                    if !state.hit_landed && distance <= reach {
+                        state.hit_landed = true;
+                        damage_events.send(DamageEvent {
+                            target: player_entity,
+                            amount: def.damage.roll(rng.stream(RngStream::Combat)),
+                            source: Some(entity),
+                        });
+                    }
+
+                    if state.phase_time >= hit_window {
+                        state.enter(AttackPhase::Recover);
+                    }
+                }
+                EnemyAttack::Ranged { .. } => state.enter(AttackPhase::Recover),
+            },
+            AttackPhase::Recover => {
+                if state.phase_time >= def.recover {
+                    state.cooldown = def.cooldown;
+                    state.enter(AttackPhase::Idle);
+                }
+            }
+        }
+    }
+}
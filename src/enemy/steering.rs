@@ -0,0 +1,98 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+/// Enemies closer than this push away from each other.
+pub const SEPARATION_RADIUS: f32 = 4.0;
+pub const SEPARATION_WEIGHT: f32 = 1.5;
+
+/// Seeking slows down linearly inside this distance of the stopping point.
+pub const ARRIVAL_RADIUS: f32 = 3.0;
+
+/// How far ahead the wall feeler is cast, and its radius.
+pub const WALL_LOOKAHEAD: f32 = 2.5;
+pub const WALL_FEELER_RADIUS: f32 = 1.2;
+
+/// How quickly the motor velocity can change, in m/s².
+pub const MAX_ACCELERATION: f32 = 20.0;
+
+/// Flattens a vector onto the ground plane.
+pub fn flat(v: Vec3) -> Vec3 {
+    Vec3::new(v.x, 0.0, v.z)
+}
+
+/// Velocity towards `target`, easing off inside [`ARRIVAL_RADIUS`] and stopping at `stop_distance`.
+pub fn arrive(position: Vec3, target: Vec3, stop_distance: f32, max_speed: f32) -> Vec3 {
+    let offset = flat(target - position);
+    let distance = offset.length() - stop_distance;
+
+    if distance <= 0.0 {
+        return Vec3::ZERO;
+    }
+
+    let speed = max_speed * (distance / ARRIVAL_RADIUS).min(1.0);
+    offset.normalize_or_zero() * speed
+}
+
+/// Push away from every neighbour inside [`SEPARATION_RADIUS`], stronger the closer they are.
+pub fn separate(
+    entity: Entity,
+    position: Vec3,
+    neighbours: &[(Entity, Vec3)],
+    max_speed: f32,
+) -> Vec3 {
+    let mut push = Vec3::ZERO;
+
+    for (other, other_pos) in neighbours {
+        if *other == entity {
+            continue;
+        }
+
+        let away = flat(position - *other_pos);
+        let distance = away.length();
+
+        if distance < SEPARATION_RADIUS && distance > f32::EPSILON {
+            push += away / distance * (1.0 - distance / SEPARATION_RADIUS);
+        }
+    }
+
+    push * max_speed * SEPARATION_WEIGHT
+}
+
+/// Casts a short feeler along `desired` and bends it away from any wall in the way.
+/// Returns the adjusted velocity and whether a wall was found.
+pub fn avoid_walls(
+    rapier_context: &RapierContext,
+    position: Vec3,
+    desired: Vec3,
+    filter: QueryFilter,
+) -> (Vec3, bool) {
+    let dir = desired.normalize_or_zero();
+
+    if dir == Vec3::ZERO {
+        return (desired, false);
+    }
+
+    let hit = rapier_context.cast_shape(
+        position,
+        Quat::IDENTITY,
+        dir,
+        &Collider::ball(WALL_FEELER_RADIUS),
+        ShapeCastOptions::with_max_time_of_impact(WALL_LOOKAHEAD),
+        filter,
+    );
+
+    let Some((_, hit)) = hit else {
+        return (desired, false);
+    };
+
+    // normal1 points from the feeler towards the wall.
+    let normal = hit
+        .details
+        .map(|details| flat(details.normal1).normalize_or_zero())
+        .unwrap_or(dir);
+
+    let urgency = 1.0 - (hit.time_of_impact / WALL_LOOKAHEAD).clamp(0.0, 1.0);
+    let slide = desired - normal * desired.dot(normal).max(0.0);
+
+    (slide - normal * desired.length() * urgency, true)
+}
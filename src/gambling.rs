@@ -0,0 +1,926 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use rand::Rng;
+
+use crate::{
+    dice::{
+        notation::DiceExpr, DiceThrow, ExpressionRolledEvent, LooseDie, RollExpressionEvent,
+        RollGroup, RollPart, RollPurpose,
+    },
+    pickup::INTERACT_DISTANCE,
+    player::components::{Eye, Inventory, Player},
+    rng::{GameRng, RngStream},
+    sprite::Billboard,
+    AddUiMessageEvent, GameResourceHandles, MaterialName,
+};
+
+const MIN_STAKE: u32 = 5;
+const STAKE_STEP: u32 = 5;
+/// Walking further than this from the tray leaves the table.
+const LEAVE_DISTANCE: f32 = 4.0;
+/// Half the inside of the tray, and the height of its walls.
+const TRAY_HALF_EXTENTS: Vec2 = Vec2::new(0.6, 0.4);
+const TRAY_WALL_HEIGHT: f32 = 0.15;
+const TRAY_WALL_THICKNESS: f32 = 0.05;
+/// Seconds a lobbed die takes to land in the middle of the tray.
+const LOB_TIME: f32 = 0.45;
+/// How long the house takes to make up its mind, so it doesn't answer instantly.
+const THINK_TIME: f32 = 1.2;
+const ROUND_OVER_TIME: f32 = 2.5;
+
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum DiceGame {
+    /// Both throw 2d6 and the higher total takes the pot. The house can fold or raise.
+    HighRoll,
+    /// Both throw 5d6 in secret, then bid on how many of a face are on the table.
+    LiarsDice,
+}
+
+impl DiceGame {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "high_roll" => Some(DiceGame::HighRoll),
+            "liars_dice" => Some(DiceGame::LiarsDice),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DiceGame::HighRoll => "high-roll",
+            DiceGame::LiarsDice => "liar's dice",
+        }
+    }
+
+    /// d6s each side throws.
+    pub fn dice_count(&self) -> u32 {
+        match self {
+            DiceGame::HighRoll => 2,
+            DiceGame::LiarsDice => 5,
+        }
+    }
+}
+
+/// Someone to play dice with. Their gold is at stake as much as the player's.
+#[derive(Component)]
+pub struct Gambler {
+    pub name: String,
+    pub game: DiceGame,
+    pub purse: u32,
+    pub tray: Entity,
+}
+
+/// A Liar's dice claim: at least `quantity` dice on the table show `face`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Bid {
+    pub quantity: u32,
+    pub face: i32,
+}
+
+impl Bid {
+    /// More dice, or as many dice showing a higher face.
+    pub fn beats(&self, other: &Bid) -> bool {
+        self.quantity > other.quantity
+            || (self.quantity == other.quantity && self.face > other.face)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TablePhase {
+    /// Up and Down change the stake, Space throws.
+    Wager,
+    /// Someone's dice are in the tray, waiting for them to settle.
+    Throwing { house: bool },
+    /// The house's dice go in next.
+    HouseThrows,
+    /// High-roll: the house weighs up the player's throw.
+    HouseThinking { seconds: f32 },
+    /// High-roll: the house raised, Y calls and X folds.
+    Raised,
+    /// Liar's dice: Up/Down pick how many, Left/Right which face, Enter bids, L calls liar.
+    PlayerBid,
+    /// Liar's dice: the house answers the player's bid.
+    HouseBid { seconds: f32 },
+    /// Pot paid out, back to wagering in a moment.
+    RoundOver { seconds: f32 },
+}
+
+/// A game in progress at one gambler's table.
+pub struct TableGame {
+    pub gambler: Entity,
+    pub game: DiceGame,
+    pub stake: u32,
+    pub phase: TablePhase,
+    pub player_faces: Vec<i32>,
+    pub house_faces: Vec<i32>,
+    /// Liar's dice: the standing bid, and whether the house made it.
+    pub bid: Option<(Bid, bool)>,
+    /// Liar's dice: the bid the player is putting together.
+    pub draft: Bid,
+    /// The house's Liar's dice, kept out of sight until someone calls.
+    pub hidden_dice: Vec<Entity>,
+}
+
+#[derive(Resource, Default)]
+pub struct GamblingTable {
+    /// `None` unless the player is sitting at a table.
+    pub game: Option<TableGame>,
+}
+
+#[derive(Event)]
+pub struct CreateGamblerEvent {
+    pub position: Vec3,
+    pub name: String,
+    pub game: DiceGame,
+    pub purse: u32,
+}
+
+pub(crate) fn init(app: &mut App) {
+    app.insert_resource(GamblingTable::default());
+    app.add_event::<CreateGamblerEvent>();
+
+    app.add_systems(FixedFirst, create_gambler_listener);
+    app.add_systems(
+        Update,
+        (
+            sit_down_system,
+            table_system.before(crate::dice::roll_expression_listener),
+            table_roll_listener,
+            hide_house_dice_system,
+        )
+            .chain(),
+    );
+}
+
+/// Run condition for systems that shouldn't see the keys used at the table.
+pub fn away_from_table(table: Res<GamblingTable>) -> bool {
+    table.game.is_none()
+}
+
+fn create_gambler_listener(
+    mut commands: Commands,
+    resources: Res<GameResourceHandles>,
+    mut events: EventReader<CreateGamblerEvent>,
+) {
+    for ev in events.read() {
+        // The tray sits on the floor in front of the gambler
+        let centre = Vec3::new(ev.position.x, 0.0, ev.position.z + 1.2);
+        let (x, z) = (TRAY_HALF_EXTENTS.x, TRAY_HALF_EXTENTS.y);
+        let (h, t) = (TRAY_WALL_HEIGHT, TRAY_WALL_THICKNESS);
+        let pieces = [
+            (
+                Vec3::new(0.0, t / 2.0, 0.0),
+                Vec3::new(x + t, t / 2.0, z + t),
+            ),
+            (
+                Vec3::new(x + t / 2.0, h / 2.0, 0.0),
+                Vec3::new(t / 2.0, h / 2.0, z),
+            ),
+            (
+                Vec3::new(-x - t / 2.0, h / 2.0, 0.0),
+                Vec3::new(t / 2.0, h / 2.0, z),
+            ),
+            (
+                Vec3::new(0.0, h / 2.0, z + t / 2.0),
+                Vec3::new(x + t, h / 2.0, t / 2.0),
+            ),
+            (
+                Vec3::new(0.0, h / 2.0, -z - t / 2.0),
+                Vec3::new(x + t, h / 2.0, t / 2.0),
+            ),
+        ];
+
+        let tray = commands
+            .spawn(SpatialBundle {
+                transform: Transform::IDENTITY.with_translation(centre),
+                ..default()
+            })
+            .with_children(|parent| {
+                for (offset, half_extents) in pieces {
+                    // The collider stays unscaled, only the mesh is stretched to fit
+                    parent
+                        .spawn(SpatialBundle {
+                            transform: Transform::IDENTITY.with_translation(offset),
+                            ..default()
+                        })
+                        .insert(RigidBody::Fixed)
+                        .insert(Collider::cuboid(
+                            half_extents.x,
+                            half_extents.y,
+                            half_extents.z,
+                        ))
+                        .with_children(|piece| {
+                            piece.spawn(PbrBundle {
+                                mesh: resources.cube.clone(),
+                                material: resources.get_material(MaterialName::RoughStone),
+                                transform: Transform::IDENTITY
+                                    .with_scale(half_extents * 2.0 / crate::tilemap::TILE_SIZE),
+                                ..default()
+                            });
+                        });
+                }
+            })
+            .id();
+
+        commands
+            .spawn(PbrBundle {
+                mesh: resources.gambler_quad.clone(),
+                material: resources.gambler_material.clone(),
+                transform: Transform::IDENTITY.with_translation(ev.position),
+                ..default()
+            })
+            .insert(Billboard)
+            .insert(RigidBody::Fixed)
+            .insert(Collider::capsule_y(0.5, 0.3))
+            .insert(Gambler {
+                name: ev.name.clone(),
+                game: ev.game,
+                purse: ev.purse,
+                tray,
+            });
+    }
+}
+
+/// E on a gambler sits down at their table.
+fn sit_down_system(
+    mut table: ResMut<GamblingTable>,
+    player_query: Query<(Entity, &Player, &Eye, &Inventory)>,
+    gambler_query: Query<&Gambler>,
+    rapier_context: Res<RapierContext>,
+    key: Res<ButtonInput<KeyCode>>,
+    mut add_message_event: EventWriter<AddUiMessageEvent>,
+) {
+    if table.game.is_some() || !key.just_pressed(KeyCode::KeyE) {
+        return;
+    }
+    let Ok((player_entity, player, eye, inventory)) = player_query.get_single() else {
+        return;
+    };
+
+    let filter = QueryFilter::default()
+        .exclude_collider(player_entity)
+        .exclude_sensors();
+    let Some((entity, _)) = rapier_context.cast_ray(
+        eye.position,
+        *eye.forward(),
+        INTERACT_DISTANCE,
+        true,
+        filter,
+    ) else {
+        return;
+    };
+    let Ok(gambler) = gambler_query.get(entity) else {
+        return;
+    };
+
+    // Throwing and walking away both wait on the dice already rolling
+    if player.dice_active {
+        add_message_event.send(AddUiMessageEvent {
+            message: format!("\"Let your dice land first,\" says {}", gambler.name),
+            duration: 2.5,
+        });
+        return;
+    }
+
+    if inventory.gold < MIN_STAKE {
+        add_message_event.send(AddUiMessageEvent {
+            message: format!(
+                "\"Come back when you've got {} gold,\" says {}",
+                MIN_STAKE, gambler.name
+            ),
+            duration: 2.5,
+        });
+        return;
+    }
+
+    add_message_event.send(AddUiMessageEvent {
+        message: format!(
+            "{} plays {} for {} gold. Up/Down stake, Space throws, walk away to leave",
+            gambler.name,
+            gambler.game.name(),
+            MIN_STAKE
+        ),
+        duration: 4.0,
+    });
+
+    table.game = Some(TableGame {
+        gambler: entity,
+        game: gambler.game,
+        stake: MIN_STAKE,
+        phase: TablePhase::Wager,
+        player_faces: Vec::new(),
+        house_faces: Vec::new(),
+        bid: None,
+        draft: Bid {
+            quantity: 1,
+            face: 2,
+        },
+        hidden_dice: Vec::new(),
+    });
+}
+
+/// Takes the player's input at the table and plays the house's side.
+fn table_system(
+    mut commands: Commands,
+    mut table: ResMut<GamblingTable>,
+    mut player_query: Query<(&Transform, &mut Inventory), With<Player>>,
+    mut gambler_query: Query<(&Transform, &mut Gambler), Without<Player>>,
+    tray_query: Query<&Transform, (Without<Player>, Without<Gambler>)>,
+    loose_query: Query<(Entity, &LooseDie)>,
+    mut visibility_query: Query<&mut Visibility>,
+    key: Res<ButtonInput<KeyCode>>,
+    time: Res<Time>,
+    rapier_config: Res<RapierConfiguration>,
+    mut rng: ResMut<GameRng>,
+    mut roll_events: EventWriter<RollExpressionEvent>,
+    mut add_message_event: EventWriter<AddUiMessageEvent>,
+) {
+    let Some(game) = table.game.as_mut() else {
+        return;
+    };
+    let Ok((player_xform, mut inventory)) = player_query.get_single_mut() else {
+        return;
+    };
+    let Ok((gambler_xform, mut gambler)) = gambler_query.get_mut(game.gambler) else {
+        table.game = None;
+        return;
+    };
+    let Ok(tray_xform) = tray_query.get(gambler.tray) else {
+        return;
+    };
+
+    let dt = time.delta_seconds();
+    let tray = gambler.tray;
+    let mut message = None;
+
+    // Dice thrown by the house end up lying in the tray, clear them between rounds
+    let mut clear_tray = |commands: &mut Commands| {
+        for (die, loose) in loose_query.iter() {
+            if loose.owner == Some(tray) {
+                commands.entity(die).despawn();
+            }
+        }
+    };
+
+    if player_xform.translation.distance(tray_xform.translation) > LEAVE_DISTANCE {
+        let forfeit = !matches!(game.phase, TablePhase::Wager | TablePhase::RoundOver { .. });
+        let text = if forfeit {
+            pay(&mut inventory, &mut gambler, game.stake, false);
+            format!("You walk away and forfeit {} gold", game.stake)
+        } else {
+            String::from("You leave the table")
+        };
+        add_message_event.send(AddUiMessageEvent {
+            message: text,
+            duration: 2.0,
+        });
+
+        clear_tray(&mut commands);
+        table.game = None;
+        return;
+    }
+
+    let mut throw = |house: bool, show: bool, rng: &mut GameRng| {
+        let from = if house {
+            gambler_xform.translation
+        } else {
+            player_xform.translation
+        };
+        roll_events.send(RollExpressionEvent {
+            roller: tray,
+            expression: DiceExpr::parse(&format!("{}d6", game.game.dice_count())).unwrap(),
+            purpose: RollPurpose::Gamble { house },
+            physical: true,
+            target: None,
+            show,
+            throw: Some(lob_into_tray(
+                tray_xform.translation,
+                from,
+                rapier_config.gravity,
+                rng.stream(RngStream::Dice),
+            )),
+            item: None,
+        });
+    };
+
+    match game.phase {
+        TablePhase::Wager => {
+            let most = inventory.gold.min(gambler.purse);
+            if key.just_pressed(KeyCode::ArrowUp) && game.stake + STAKE_STEP <= most {
+                game.stake += STAKE_STEP;
+                message = Some(format!("Stake: {} gold", game.stake));
+            }
+            if key.just_pressed(KeyCode::ArrowDown) && game.stake > MIN_STAKE {
+                game.stake = (game.stake - STAKE_STEP).max(MIN_STAKE);
+                message = Some(format!("Stake: {} gold", game.stake));
+            }
+
+            if key.just_pressed(KeyCode::Space) {
+                if inventory.gold < game.stake {
+                    message = Some(String::from("You can't cover that stake"));
+                } else if gambler.purse < game.stake {
+                    message = Some(format!("{} can't cover that stake", gambler.name));
+                } else {
+                    clear_tray(&mut commands);
+                    game.player_faces.clear();
+                    game.house_faces.clear();
+                    game.hidden_dice.clear();
+                    game.bid = None;
+
+                    throw(false, true, &mut *rng);
+                    game.phase = TablePhase::Throwing { house: false };
+                }
+            }
+        }
+        TablePhase::HouseThrows => {
+            // Liar's dice are thrown in secret
+            throw(true, game.game != DiceGame::LiarsDice, &mut *rng);
+            game.phase = TablePhase::Throwing { house: true };
+        }
+        TablePhase::HouseThinking { seconds } if seconds > dt => {
+            game.phase = TablePhase::HouseThinking {
+                seconds: seconds - dt,
+            };
+        }
+        TablePhase::HouseThinking { .. } => {
+            let player_total: i32 = game.player_faces.iter().sum();
+            let (above, equal) = total_odds(game.game.dice_count(), player_total);
+            let chance = above + equal / 2.0 + rng.stream(RngStream::Ai).gen_range(-0.05..0.05);
+            let raise = game.stake * 2;
+
+            if chance < 0.25 {
+                pay(&mut inventory, &mut gambler, game.stake, true);
+                message = Some(format!(
+                    "{} folds. You win {} gold",
+                    gambler.name, game.stake
+                ));
+                game.phase = TablePhase::RoundOver {
+                    seconds: ROUND_OVER_TIME,
+                };
+            } else if chance > 0.6 && gambler.purse >= raise && inventory.gold >= raise {
+                message = Some(format!(
+                    "{} raises to {} gold. Y to call, X to fold",
+                    gambler.name, raise
+                ));
+                game.phase = TablePhase::Raised;
+            } else {
+                message = Some(format!("{} calls", gambler.name));
+                game.phase = TablePhase::HouseThrows;
+            }
+        }
+        TablePhase::Raised => {
+            if key.just_pressed(KeyCode::KeyY) {
+                game.stake *= 2;
+                game.phase = TablePhase::HouseThrows;
+            } else if key.just_pressed(KeyCode::KeyX) {
+                pay(&mut inventory, &mut gambler, game.stake, false);
+                message = Some(format!("You fold and lose {} gold", game.stake));
+                game.phase = TablePhase::RoundOver {
+                    seconds: ROUND_OVER_TIME,
+                };
+            }
+        }
+        TablePhase::PlayerBid => {
+            let table_dice = game.game.dice_count() * 2;
+            let draft = &mut game.draft;
+            let before = *draft;
+
+            if key.just_pressed(KeyCode::ArrowUp) {
+                draft.quantity = (draft.quantity + 1).min(table_dice);
+            }
+            if key.just_pressed(KeyCode::ArrowDown) {
+                draft.quantity = draft.quantity.saturating_sub(1).max(1);
+            }
+            if key.just_pressed(KeyCode::ArrowRight) {
+                draft.face = draft.face % 6 + 1;
+            }
+            if key.just_pressed(KeyCode::ArrowLeft) {
+                draft.face = (draft.face + 4) % 6 + 1;
+            }
+            if *draft != before {
+                message = Some(format!("Bid: {} x {}", draft.quantity, draft.face));
+            }
+
+            if key.just_pressed(KeyCode::Enter) {
+                let draft = game.draft;
+                match game.bid {
+                    Some((bid, _)) if !draft.beats(&bid) => {
+                        message = Some(format!("You have to beat {} x {}", bid.quantity, bid.face));
+                    }
+                    _ => {
+                        game.bid = Some((draft, false));
+                        message = Some(format!("You bid {} x {}", draft.quantity, draft.face));
+                        game.phase = TablePhase::HouseBid {
+                            seconds: THINK_TIME,
+                        };
+                    }
+                }
+            } else if key.just_pressed(KeyCode::KeyL) {
+                if let Some((bid, true)) = game.bid {
+                    message = Some(reveal(
+                        game,
+                        &mut gambler,
+                        &mut inventory,
+                        &mut visibility_query,
+                        bid,
+                        false,
+                    ));
+                }
+            }
+        }
+        TablePhase::HouseBid { seconds } if seconds > dt => {
+            game.phase = TablePhase::HouseBid {
+                seconds: seconds - dt,
+            };
+        }
+        TablePhase::HouseBid { .. } => {
+            let Some((bid, _)) = game.bid else {
+                return;
+            };
+            let unknown = game.game.dice_count();
+            let rng = rng.stream(RngStream::Ai);
+
+            // Call when the player's bid looks unlikely, otherwise make the safest raise
+            // there is, with a little noise so the house bluffs now and then.
+            if bid_odds(bid, &game.house_faces, unknown) < 0.35 {
+                message = Some(format!("{} calls you a liar!", gambler.name));
+                let result = reveal(
+                    game,
+                    &mut gambler,
+                    &mut inventory,
+                    &mut visibility_query,
+                    bid,
+                    true,
+                );
+                message = message.map(|m| format!("{} {}", m, result));
+            } else {
+                let raise = (1..=6)
+                    .map(|face| Bid {
+                        quantity: if face > bid.face {
+                            bid.quantity
+                        } else {
+                            bid.quantity + 1
+                        },
+                        face,
+                    })
+                    .filter(|raise| raise.quantity <= unknown * 2)
+                    .map(|raise| {
+                        let odds = bid_odds(raise, &game.house_faces, unknown);
+                        (raise, odds + rng.gen_range(0.0..0.15))
+                    })
+                    .max_by(|a, b| a.1.total_cmp(&b.1));
+
+                match raise {
+                    Some((raise, _)) => {
+                        game.bid = Some((raise, true));
+                        game.draft = raise;
+                        message = Some(format!(
+                            "{} bids {} x {}. Raise with Enter or call with L",
+                            gambler.name, raise.quantity, raise.face
+                        ));
+                        game.phase = TablePhase::PlayerBid;
+                    }
+                    // Nothing left to bid, so the house has to call
+                    None => {
+                        message = Some(reveal(
+                            game,
+                            &mut gambler,
+                            &mut inventory,
+                            &mut visibility_query,
+                            bid,
+                            true,
+                        ));
+                    }
+                }
+            }
+        }
+        TablePhase::RoundOver { seconds } if seconds > dt => {
+            game.phase = TablePhase::RoundOver {
+                seconds: seconds - dt,
+            };
+        }
+        TablePhase::RoundOver { .. } => {
+            game.phase = TablePhase::Wager;
+            message = Some(format!(
+                "Stake: {} gold. Space to play again",
+                game.stake.min(inventory.gold).max(MIN_STAKE)
+            ));
+            game.stake = game.stake.min(inventory.gold).max(MIN_STAKE);
+        }
+        TablePhase::Throwing { .. } => {}
+    }
+
+    if let Some(message) = message {
+        add_message_event.send(AddUiMessageEvent {
+            message,
+            duration: 3.0,
+        });
+    }
+}
+
+/// Reads the dice once they settle in the tray.
+fn table_roll_listener(
+    mut table: ResMut<GamblingTable>,
+    mut player_query: Query<&mut Inventory, With<Player>>,
+    mut gambler_query: Query<&mut Gambler>,
+    mut rolled_events: EventReader<ExpressionRolledEvent>,
+    mut add_message_event: EventWriter<AddUiMessageEvent>,
+) {
+    for ev in rolled_events.read() {
+        let RollPurpose::Gamble { house } = ev.purpose else {
+            continue;
+        };
+        let Some(game) = table.game.as_mut() else {
+            continue;
+        };
+        if game.phase != (TablePhase::Throwing { house }) {
+            continue;
+        }
+
+        let faces: Vec<i32> = ev
+            .result
+            .dice
+            .iter()
+            .filter(|die| die.kept)
+            .map(|die| die.value)
+            .collect();
+
+        match (game.game, house) {
+            (DiceGame::HighRoll, false) => {
+                game.player_faces = faces;
+                game.phase = TablePhase::HouseThinking {
+                    seconds: THINK_TIME,
+                };
+            }
+            (DiceGame::HighRoll, true) => {
+                game.house_faces = faces;
+                let (Ok(mut inventory), Ok(mut gambler)) = (
+                    player_query.get_single_mut(),
+                    gambler_query.get_mut(game.gambler),
+                ) else {
+                    continue;
+                };
+
+                let player_total: i32 = game.player_faces.iter().sum();
+                let house_total: i32 = game.house_faces.iter().sum();
+                let message = if player_total == house_total {
+                    format!("{} each, a push", player_total)
+                } else {
+                    let won = player_total > house_total;
+                    pay(&mut inventory, &mut gambler, game.stake, won);
+                    format!(
+                        "{} against {}, you {} {} gold",
+                        player_total,
+                        house_total,
+                        if won { "win" } else { "lose" },
+                        game.stake
+                    )
+                };
+                add_message_event.send(AddUiMessageEvent {
+                    message,
+                    duration: 3.0,
+                });
+                game.phase = TablePhase::RoundOver {
+                    seconds: ROUND_OVER_TIME,
+                };
+            }
+            (DiceGame::LiarsDice, false) => {
+                game.player_faces = faces;
+                game.phase = TablePhase::HouseThrows;
+            }
+            (DiceGame::LiarsDice, true) => {
+                game.house_faces = faces;
+                add_message_event.send(AddUiMessageEvent {
+                    message: format!(
+                        "Your bid: {} x {}. Arrows change it, Enter bids",
+                        game.draft.quantity, game.draft.face
+                    ),
+                    duration: 3.0,
+                });
+                game.phase = TablePhase::PlayerBid;
+            }
+        }
+    }
+}
+
+/// Keeps the house's Liar's dice out of sight as they land.
+fn hide_house_dice_system(
+    mut commands: Commands,
+    mut table: ResMut<GamblingTable>,
+    query: Query<(Entity, &RollPart), Added<RollPart>>,
+    group_query: Query<&RollGroup>,
+) {
+    let Some(game) = table.game.as_mut() else {
+        return;
+    };
+    if game.game != DiceGame::LiarsDice {
+        return;
+    }
+
+    for (die, part) in query.iter() {
+        let Ok(group) = group_query.get(part.0) else {
+            continue;
+        };
+        if group.purpose == (RollPurpose::Gamble { house: true }) {
+            commands.entity(die).insert(Visibility::Hidden);
+            game.hidden_dice.push(die);
+        }
+    }
+}
+
+/// Lifts the cup on a Liar's dice bid and pays whoever was right.
+fn reveal(
+    game: &mut TableGame,
+    gambler: &mut Gambler,
+    inventory: &mut Inventory,
+    visibility_query: &mut Query<&mut Visibility>,
+    bid: Bid,
+    house_called: bool,
+) -> String {
+    for die in game.hidden_dice.drain(..) {
+        if let Ok(mut visibility) = visibility_query.get_mut(die) {
+            *visibility = Visibility::Inherited;
+        }
+    }
+
+    let count = game
+        .player_faces
+        .iter()
+        .chain(game.house_faces.iter())
+        .filter(|face| **face == bid.face)
+        .count() as u32;
+    let won = player_wins_call(count >= bid.quantity, house_called);
+
+    pay(inventory, gambler, game.stake, won);
+    game.phase = TablePhase::RoundOver {
+        seconds: ROUND_OVER_TIME,
+    };
+
+    format!(
+        "{} {}s on the table, you {} {} gold",
+        count,
+        bid.face,
+        if won { "win" } else { "lose" },
+        game.stake
+    )
+}
+
+/// A true bid wins for the bidder, a false one for whoever called it. The player made the
+/// bid if the house called it.
+fn player_wins_call(bid_true: bool, house_called: bool) -> bool {
+    bid_true == house_called
+}
+
+fn pay(inventory: &mut Inventory, gambler: &mut Gambler, stake: u32, player_won: bool) {
+    if player_won {
+        let stake = stake.min(gambler.purse);
+        gambler.purse -= stake;
+        inventory.gold += stake;
+    } else {
+        let stake = stake.min(inventory.gold);
+        inventory.gold -= stake;
+        gambler.purse += stake;
+    }
+}
+
+/// A throw from the rim of the tray nearest `from` that lands in its middle.
+fn lob_into_tray(centre: Vec3, from: Vec3, gravity: Vec3, rng: &mut impl Rng) -> DiceThrow {
+    let side = Vec3::new(from.x - centre.x, 0.0, from.z - centre.z).normalize_or_zero();
+    let origin = centre + side * (TRAY_HALF_EXTENTS.x + 0.3) + Vec3::Y * 0.6;
+    let target = centre + Vec3::Y * 0.1;
+
+    DiceThrow {
+        origin,
+        velocity: (target - origin) / LOB_TIME - gravity * LOB_TIME / 2.0,
+        spin: crate::mathx::random::vec3(rng) * rng.gen_range(8.0..16.0),
+    }
+}
+
+/// Chances of `count` d6 totalling more than `total`, and exactly `total`.
+fn total_odds(count: u32, total: i32) -> (f32, f32) {
+    // ways[t] is the number of ways to roll a total of t
+    let mut ways = vec![1.0f32];
+    for _ in 0..count {
+        let mut next = vec![0.0; ways.len() + 6];
+        for (t, n) in ways.iter().enumerate() {
+            for face in 1..=6 {
+                next[t + face] += n;
+            }
+        }
+        ways = next;
+    }
+
+    let all: f32 = ways.iter().sum();
+    let above: f32 = ways.iter().skip((total + 1).max(0) as usize).sum();
+    let equal = ways.get(total.max(0) as usize).copied().unwrap_or(0.0);
+
+    (above / all, equal / all)
+}
+
+/// Chance `bid` is true, knowing `own` faces and not the other `unknown` dice.
+fn bid_odds(bid: Bid, own: &[i32], unknown: u32) -> f32 {
+    let have = own.iter().filter(|face| **face == bid.face).count() as u32;
+    let need = bid.quantity.saturating_sub(have);
+
+    // At least `need` of the unknown dice show the face
+    let p = 1.0 / 6.0f32;
+    (need..=unknown)
+        .map(|k| binomial(unknown, k) * p.powi(k as i32) * (1.0 - p).powi((unknown - k) as i32))
+        .sum()
+}
+
+fn binomial(n: u32, k: u32) -> f32 {
+    (0..k).fold(1.0, |acc, i| acc * (n - i) as f32 / (i + 1) as f32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-5
+    }
+
+    fn gambler(purse: u32) -> Gambler {
+        Gambler {
+            name: String::from("Test"),
+            game: DiceGame::LiarsDice,
+            purse,
+            tray: Entity::PLACEHOLDER,
+        }
+    }
+
+    #[test]
+    fn bids_beat_on_quantity_then_face() {
+        let bid = |quantity, face| Bid { quantity, face };
+
+        assert!(bid(3, 2).beats(&bid(2, 6)));
+        assert!(bid(2, 5).beats(&bid(2, 4)));
+        assert!(!bid(2, 4).beats(&bid(2, 4)));
+        assert!(!bid(2, 3).beats(&bid(2, 4)));
+        assert!(!bid(1, 6).beats(&bid(2, 1)));
+    }
+
+    #[test]
+    fn total_odds_of_two_dice() {
+        let (above, equal) = total_odds(2, 7);
+        assert!(close(above, 15.0 / 36.0), "{}", above);
+        assert!(close(equal, 6.0 / 36.0), "{}", equal);
+
+        assert_eq!(total_odds(2, 12), (0.0, 1.0 / 36.0));
+        assert_eq!(total_odds(2, 1), (1.0, 0.0));
+    }
+
+    #[test]
+    fn binomial_coefficients() {
+        assert_eq!(binomial(5, 0), 1.0);
+        assert_eq!(binomial(5, 2), 10.0);
+        assert_eq!(binomial(5, 5), 1.0);
+        assert_eq!(binomial(10, 3), 120.0);
+    }
+
+    #[test]
+    fn bid_odds_count_own_dice() {
+        let bid = Bid {
+            quantity: 2,
+            face: 4,
+        };
+
+        // Two fours in hand already make it true
+        assert!(close(bid_odds(bid, &[4, 4, 1], 5), 1.0));
+        // One in hand needs at least one of five unknown dice
+        let odds = bid_odds(bid, &[4, 1, 2], 5);
+        assert!(close(odds, 1.0 - (5.0f32 / 6.0).powi(5)), "{}", odds);
+        // Can't be true with too few dice left
+        assert!(close(bid_odds(bid, &[1, 2], 1), 0.0));
+    }
+
+    #[test]
+    fn calls_go_to_whoever_was_right() {
+        // The house called the player's bid
+        assert!(player_wins_call(true, true));
+        assert!(!player_wins_call(false, true));
+        // The player called the house's bid
+        assert!(!player_wins_call(true, false));
+        assert!(player_wins_call(false, false));
+    }
+
+    #[test]
+    fn pay_moves_the_stake_up_to_what_they_have() {
+        let mut inventory = Inventory {
+            gold: 20,
+            ..default()
+        };
+        let mut house = gambler(8);
+
+        pay(&mut inventory, &mut house, 10, true);
+        assert_eq!((inventory.gold, house.purse), (28, 0));
+
+        pay(&mut inventory, &mut house, 10, false);
+        assert_eq!((inventory.gold, house.purse), (18, 10));
+
+        inventory.gold = 3;
+        pay(&mut inventory, &mut house, 10, false);
+        assert_eq!((inventory.gold, house.purse), (0, 13));
+    }
+}
@@ -1,14 +1,23 @@
 #![allow(warnings)]
 
 mod camera;
+mod cards;
+mod combat;
+mod dice;
+mod encounter;
 mod enemy;
+mod gambling;
 mod mathx;
+mod pickup;
 mod player;
 mod resources;
+mod rng;
+mod spawner;
 mod sprite;
 mod tilemap;
 mod ui;
 mod utils;
+mod weapon;
 mod windows;
 
 use bevy_sprite3d::Sprite3dPlugin;
@@ -42,6 +51,16 @@ use bevy_obj::ObjPlugin;
 use bevy_rapier3d::{control, prelude::*};
 
 fn main() {
+    // Headless dice fairness check, see dice::simulate.
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(i) = args.iter().position(|arg| arg == "--dice-fairness") {
+        let throws = args.get(i + 1).and_then(|a| a.parse().ok()).unwrap_or(2000);
+        let sigmas = args.get(i + 2).and_then(|a| a.parse().ok()).unwrap_or(4.0);
+
+        let fair = dice::simulate::run(throws, sigmas);
+        std::process::exit(if fair { 0 } else { 1 });
+    }
+
     let mut app = App::new();
 
     // Plugins
@@ -69,7 +88,18 @@ fn main() {
                 }),
         );
         app.add_plugins(ObjPlugin);
-        app.add_plugins(RapierPhysicsPlugin::<NoUserData>::default());
+        // Physics steps once per FixedUpdate, so the same seed throws the same dice whatever
+        // the frame rate. Anything that counts steps runs there too. The plugin keeps a
+        // configuration that is already there.
+        app.insert_resource(RapierConfiguration {
+            timestep_mode: TimestepMode::Fixed {
+                dt: 1.0 / 60.0,
+                substeps: 1,
+            },
+            ..RapierConfiguration::new(1.0)
+        });
+        app.add_plugins(RapierPhysicsPlugin::<NoUserData>::default().in_fixed_schedule());
+        app.insert_resource(Time::<Fixed>::from_hz(60.0));
         app.add_plugins(RapierDebugRenderPlugin {
             enabled: false,
             ..default()
@@ -79,8 +109,12 @@ fn main() {
 
     // Resources
     {
-        app.insert_resource(UserSettings { mouse_sens: 0.005 })
-            .insert_resource(GameResourceHandles::default());
+        app.insert_resource(UserSettings {
+            mouse_sens: 0.005,
+            scene_look: false,
+            camera_effects: camera::effects::CameraEffectSettings::default(),
+        })
+        .insert_resource(GameResourceHandles::default());
 
         app.insert_resource(CameraState::default());
         app.insert_resource(CameraParameters(PhysicalCameraParameters {
@@ -94,14 +128,23 @@ fn main() {
     }
 
     // Modules init Events, Listeners, and Systems.
+    rng::init(&mut app);
     resources::init(&mut app);
     windows::init(&mut app);
     ui::init(&mut app);
     player::init(&mut app);
     tilemap::init(&mut app);
     camera::init(&mut app);
+    cards::init(&mut app);
+    combat::init(&mut app);
+    dice::init(&mut app);
+    encounter::init(&mut app);
     enemy::init(&mut app);
+    gambling::init(&mut app);
+    pickup::init(&mut app);
+    spawner::init(&mut app);
     sprite::init(&mut app);
+    weapon::init(&mut app);
 
     // Systems
     app.add_systems(Startup, start);
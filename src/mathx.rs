@@ -28,18 +28,14 @@ pub mod random {
     use bevy::math::{EulerRot, Quat, Vec2, Vec3};
     use rand::prelude::*;
 
-    pub fn vec2() -> Vec2 {
-        let mut rng = rand::thread_rng();
-
+    pub fn vec2(rng: &mut impl Rng) -> Vec2 {
         let ran_x: f32 = rng.gen_range(-1.0..1.0);
         let ran_y: f32 = rng.gen_range(-1.0..1.0);
 
         return bevy::math::vec2(ran_x, ran_y);
     }
 
-    pub fn vec3() -> Vec3 {
-        let mut rng = rand::thread_rng();
-
+    pub fn vec3(rng: &mut impl Rng) -> Vec3 {
         let ran_x: f32 = rng.gen_range(-1.0..1.0);
         let ran_y: f32 = rng.gen_range(-1.0..1.0);
         let ran_z: f32 = rng.gen_range(-1.0..1.0);
@@ -47,9 +43,7 @@ pub mod random {
         return bevy::math::vec3(ran_x, ran_y, ran_z);
     }
 
-    pub fn quat() -> Quat {
-        let mut rng = rand::thread_rng();
-
+    pub fn quat(rng: &mut impl Rng) -> Quat {
         let ran_x: f32 = rng.gen_range(-1.0..1.0);
         let ran_y: f32 = rng.gen_range(-1.0..1.0);
         let ran_z: f32 = rng.gen_range(-1.0..1.0);
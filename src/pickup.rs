@@ -0,0 +1,315 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use rand::{seq::SliceRandom, Rng};
+
+use crate::{
+    combat::{damage_listener, DeathEvent, Health},
+    dice::items::DiceItem,
+    enemy::Enemy,
+    player::components::{Eye, Inventory, Player, MAX_DICE_CARRIED},
+    rng::{GameRng, RngStream},
+    sprite::Billboard,
+    AddUiMessageEvent, GameResourceHandles,
+};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PickupKind {
+    Health(i32),
+    Gold(u32),
+    Key(String),
+    Dice(u32),
+    Item(String),
+    DiceItem(DiceItem),
+}
+
+#[derive(Eq, PartialEq, Hash, Clone, Copy, Debug)]
+pub enum PickupIcon {
+    Potion,
+    Coin,
+    Key,
+    Die,
+    Gem,
+}
+
+impl PickupKind {
+    /// Builds a pickup from a name such as `gold`, with `amount` and `label` used where they apply.
+    pub fn from_name(name: &str, amount: i32, label: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "health" => Some(PickupKind::Health(amount)),
+            "gold" => Some(PickupKind::Gold(amount.max(0) as u32)),
+            "key" => Some(PickupKind::Key(String::from(label))),
+            "dice" => Some(PickupKind::Dice(amount.max(1) as u32)),
+            "item" => Some(PickupKind::Item(String::from(label))),
+            "dice_item" => DiceItem::from_name(label).map(PickupKind::DiceItem),
+            _ => None,
+        }
+    }
+
+    pub fn icon(&self) -> PickupIcon {
+        match self {
+            PickupKind::Health(_) => PickupIcon::Potion,
+            PickupKind::Gold(_) => PickupIcon::Coin,
+            PickupKind::Key(_) => PickupIcon::Key,
+            PickupKind::Dice(_) => PickupIcon::Die,
+            PickupKind::Item(_) => PickupIcon::Gem,
+            PickupKind::DiceItem(_) => PickupIcon::Die,
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            PickupKind::Health(amount) => format!("Drank a potion (+{} hp)", amount),
+            PickupKind::Gold(amount) => format!("Picked up {} gold", amount),
+            PickupKind::Key(name) => format!("Picked up the {} key", name),
+            PickupKind::Dice(1) => String::from("Picked up a die"),
+            PickupKind::Dice(amount) => format!("Picked up {} dice", amount),
+            PickupKind::Item(name) => format!("Picked up {}", name),
+            PickupKind::DiceItem(item) => format!("Found a {}", item.def().name),
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct Pickup {
+    pub kind: PickupKind,
+    /// Collected by walking over it, otherwise the player has to interact with it.
+    pub auto_collect: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct LootEntry {
+    /// `None` is a roll that drops nothing.
+    pub kind: Option<PickupKind>,
+    pub weight: u32,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct LootTable {
+    pub rolls: u32,
+    pub entries: Vec<LootEntry>,
+}
+
+impl LootTable {
+    pub fn roll(&self, rng: &mut impl Rng) -> Vec<PickupKind> {
+        (0..self.rolls)
+            .filter_map(|_| {
+                self.entries
+                    .choose_weighted(rng, |e| e.weight)
+                    .ok()
+                    .and_then(|e| e.kind.clone())
+            })
+            .collect()
+    }
+}
+
+#[derive(Event)]
+pub struct CreatePickupEvent {
+    pub position: Vec3,
+    pub kind: PickupKind,
+    pub auto_collect: bool,
+}
+
+#[derive(Event)]
+pub struct PickupCollectedEvent {
+    pub collector: Entity,
+    pub kind: PickupKind,
+}
+
+pub(crate) const INTERACT_DISTANCE: f32 = 2.5;
+const PICKUP_RADIUS: f32 = 0.6;
+/// Height of a pickup's centre above the floor.
+const PICKUP_HEIGHT: f32 = 0.6;
+
+pub(crate) fn init(app: &mut App) {
+    app.add_event::<CreatePickupEvent>();
+    app.add_event::<PickupCollectedEvent>();
+
+    app.add_systems(FixedFirst, create_pickup_listener);
+    app.add_systems(
+        Update,
+        (
+            drop_loot_listener.after(damage_listener),
+            pickup_system,
+            pickup_bob,
+        ),
+    );
+}
+
+fn create_pickup_listener(
+    mut commands: Commands,
+    resources: Res<GameResourceHandles>,
+    mut events: EventReader<CreatePickupEvent>,
+) {
+    for ev in events.read() {
+        commands
+            .spawn(SpatialBundle {
+                transform: Transform::IDENTITY.with_translation(ev.position),
+                ..default()
+            })
+            .insert(Pickup {
+                kind: ev.kind.clone(),
+                auto_collect: ev.auto_collect,
+            })
+            .insert(Collider::ball(PICKUP_RADIUS))
+            .insert(Sensor)
+            .with_children(|parent| {
+                parent
+                    .spawn(PbrBundle {
+                        mesh: resources.pickup_quad.clone(),
+                        material: resources
+                            .pickup_materials
+                            .get(&ev.kind.icon())
+                            .unwrap()
+                            .clone(),
+                        ..default()
+                    })
+                    .insert(Billboard);
+            });
+    }
+}
+
+fn drop_loot_listener(
+    query: Query<(Entity, &Enemy)>,
+    rapier_context: Res<RapierContext>,
+    mut rng: ResMut<GameRng>,
+    mut events: EventReader<DeathEvent>,
+    mut pickup_events: EventWriter<CreatePickupEvent>,
+) {
+    for ev in events.read() {
+        let Ok((entity, enemy)) = query.get(ev.entity) else {
+            continue;
+        };
+
+        // Drop onto whatever is below the corpse.
+        let filter = QueryFilter::exclude_dynamic()
+            .exclude_collider(entity)
+            .exclude_sensors();
+        let floor = rapier_context
+            .cast_ray(ev.position, Vec3::NEG_Y, 10.0, true, filter)
+            .map_or(ev.position.y, |(_, toi)| ev.position.y - toi);
+
+        let rng = rng.stream(RngStream::Loot);
+
+        for kind in enemy.kind.loot_table().roll(rng) {
+            let scatter = crate::mathx::random::vec2(rng) * 0.8;
+
+            pickup_events.send(CreatePickupEvent {
+                position: Vec3::new(
+                    ev.position.x + scatter.x,
+                    floor + PICKUP_HEIGHT,
+                    ev.position.z + scatter.y,
+                ),
+                kind,
+                auto_collect: true,
+            });
+        }
+    }
+}
+
+fn pickup_system(
+    mut commands: Commands,
+    mut player_query: Query<
+        (
+            Entity,
+            &Transform,
+            &Collider,
+            &Eye,
+            &mut Inventory,
+            &mut Health,
+        ),
+        With<Player>,
+    >,
+    pickup_query: Query<&Pickup>,
+    rapier_context: Res<RapierContext>,
+    key: Res<ButtonInput<KeyCode>>,
+    mut collected_events: EventWriter<PickupCollectedEvent>,
+    mut add_message_event: EventWriter<AddUiMessageEvent>,
+) {
+    if player_query.is_empty() {
+        return;
+    }
+
+    let (player_entity, player_xform, player_collider, eye, mut inventory, mut health) =
+        player_query.single_mut();
+
+    let mut to_collect: Vec<Entity> = Vec::new();
+
+    // Walk-over pickups touching the player's capsule
+    let filter = QueryFilter::default().exclude_collider(player_entity);
+    rapier_context.intersections_with_shape(
+        player_xform.translation,
+        player_xform.rotation,
+        player_collider,
+        filter,
+        |entity| {
+            if let Ok(pickup) = pickup_query.get(entity) {
+                let wasted =
+                    matches!(pickup.kind, PickupKind::Health(_)) && health.current >= health.max;
+
+                if pickup.auto_collect && !wasted {
+                    to_collect.push(entity);
+                }
+            }
+            true
+        },
+    );
+
+    // Anything else has to be looked at and used
+    if key.just_pressed(KeyCode::KeyE) {
+        if let Some((entity, _)) = rapier_context.cast_ray(
+            eye.position,
+            *eye.forward(),
+            INTERACT_DISTANCE,
+            true,
+            filter,
+        ) {
+            if pickup_query.contains(entity) && !to_collect.contains(&entity) {
+                to_collect.push(entity);
+            }
+        }
+    }
+
+    for entity in to_collect {
+        let Ok(pickup) = pickup_query.get(entity) else {
+            continue;
+        };
+
+        match &pickup.kind {
+            PickupKind::Health(amount) => {
+                health.current = (health.current + amount).min(health.max);
+            }
+            PickupKind::Gold(amount) => inventory.gold += amount,
+            PickupKind::Key(name) => inventory.keys.push(name.clone()),
+            PickupKind::Dice(amount) => {
+                inventory.dice = (inventory.dice + amount).min(MAX_DICE_CARRIED)
+            }
+            PickupKind::Item(name) => inventory.items.push(name.clone()),
+            PickupKind::DiceItem(item) => inventory.dice_items.push(*item),
+        }
+
+        add_message_event.send(AddUiMessageEvent {
+            message: pickup.kind.describe(),
+            duration: 2.0,
+        });
+        collected_events.send(PickupCollectedEvent {
+            collector: player_entity,
+            kind: pickup.kind.clone(),
+        });
+
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn pickup_bob(
+    mut query: Query<(&Parent, &mut Transform), With<Billboard>>,
+    pickup_query: Query<(), With<Pickup>>,
+    time: Res<Time>,
+) {
+    let t = time.elapsed_seconds();
+
+    for (parent, mut xform) in query.iter_mut() {
+        if pickup_query.contains(parent.get()) {
+            xform.translation.y = (t * 2.5).sin() * 0.08;
+        }
+    }
+}
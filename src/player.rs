@@ -5,14 +5,24 @@ pub mod systems;
 use bevy::app::App;
 use bevy::app::FixedMain;
 use bevy::app::Update;
+use bevy::ecs::schedule::IntoSystemConfigs;
+
+use crate::{encounter::exploring, gambling::away_from_table};
 
 use self::events::*;
 use self::systems::*;
 
 pub(crate) fn init(mut app: &mut App) {
     app.add_event::<SpawnPlayerEvent>();
-    app.add_event::<DiceRollEvent>();
 
     app.add_systems(FixedMain, spawn_player_listener);
-    app.add_systems(Update, (move_player, move_light, dice_system));
+    app.add_systems(Update, (player_death_listener, dice_settled_listener));
+    app.add_systems(
+        Update,
+        (
+            move_player,
+            move_light,
+            dice_system.run_if(exploring).run_if(away_from_table),
+        ),
+    );
 }
@@ -1,10 +1,27 @@
 use bevy::ecs::{bundle::Bundle, component::Component};
-use bevy::math::{Dir3, Quat, Vec3};
+use bevy::math::{Dir3, Quat, Vec2, Vec3};
 use bevy_rapier3d::control::*;
-use bevy_rapier3d::dynamics::{AdditionalMassProperties, ExternalImpulse, RigidBody, Velocity};
+use bevy_rapier3d::dynamics::{
+    AdditionalMassProperties, ExternalImpulse, RigidBody, Sleeping, Velocity,
+};
 use bevy_rapier3d::geometry::{ActiveEvents, Collider};
 use bevy_rapier3d::plugin::systems::RigidBodyWritebackComponents;
 
+use crate::{
+    combat::{Armour, Health},
+    dice::{checks::Abilities, items::DiceItem, DiceKind},
+    weapon::WeaponInventory,
+};
+
+pub const PLAYER_MAX_HEALTH: i32 = 20;
+pub const PLAYER_ARMOUR: i32 = 12;
+pub const PLAYER_SPAWN_POSITION: Vec3 = Vec3::new(4.0, 5.0, 4.0);
+/// Most dice the player can throw at once.
+pub const MAX_DICE_POOL: u32 = 8;
+/// Dice the player starts with and the most they can carry.
+pub const STARTING_DICE: u32 = 4;
+pub const MAX_DICE_CARRIED: u32 = 12;
+
 #[derive(Bundle)]
 pub struct PlayerBundle {
     pub player: Player,
@@ -12,6 +29,11 @@ pub struct PlayerBundle {
     pub move_flags: MoveFlags,
     pub controller: bevy_rapier3d::control::KinematicCharacterController,
     pub collider: Collider,
+    pub weapons: WeaponInventory,
+    pub health: Health,
+    pub inventory: Inventory,
+    pub abilities: Abilities,
+    pub armour: Armour,
 }
 
 impl Default for PlayerBundle {
@@ -26,6 +48,14 @@ impl Default for PlayerBundle {
                 ..KinematicCharacterController::default()
             },
             collider: Collider::capsule_y(0.885, 0.25),
+            weapons: WeaponInventory::default(),
+            health: Health::new(PLAYER_MAX_HEALTH),
+            inventory: Inventory {
+                dice: STARTING_DICE,
+                ..Inventory::default()
+            },
+            abilities: Abilities::default(),
+            armour: Armour(PLAYER_ARMOUR),
         }
     }
 }
@@ -34,6 +64,15 @@ impl Default for PlayerBundle {
 pub struct Player {
     pub velocity: Vec3,
     pub dice_active: bool,
+    /// Dice thrown with Space, `dice_count` of `dice_kind`.
+    pub dice_kind: DiceKind,
+    pub dice_count: u32,
+    /// Seconds Space has been held for an aimed throw, `None` when not charging.
+    pub throw_charge: Option<f32>,
+    /// Smoothed mouse movement in pixels per second, flicking on release spins the dice.
+    pub throw_flick: Vec2,
+    /// Index into [`Inventory::dice_items`] to throw instead of the plain pool.
+    pub dice_item: Option<usize>,
 }
 
 impl Default for Player {
@@ -41,10 +80,24 @@ impl Default for Player {
         Self {
             velocity: Vec3::ZERO,
             dice_active: false,
+            dice_kind: DiceKind::D6,
+            dice_count: 1,
+            throw_charge: None,
+            throw_flick: Vec2::ZERO,
+            dice_item: None,
         }
     }
 }
 
+#[derive(Component, Default)]
+pub struct Inventory {
+    pub gold: u32,
+    pub keys: Vec<String>,
+    pub dice: u32,
+    pub items: Vec<String>,
+    pub dice_items: Vec<DiceItem>,
+}
+
 #[derive(Component)]
 pub struct PlayerLight;
 
@@ -67,15 +120,36 @@ impl Default for MoveFlags {
 
 #[derive(Component)]
 pub struct Dice {
+    pub kind: DiceKind,
     pub rolled: bool,
-    pub since_landed: f32,
+    /// Face showing once `rolled` is set.
+    pub value: i32,
+    /// Read while tilted, see [`crate::dice::DiceSettings`].
+    pub cocked: bool,
+    /// Fixed steps in a row spent barely moving.
+    pub still_frames: u32,
+    /// Seconds since the last throw, reroll or nudge.
+    pub since_thrown: f32,
+    /// Times it was rerolled or nudged for landing cocked.
+    pub retries: u32,
+    /// Last frame's speed, for impact damage.
+    pub speed: f32,
+    /// Set for special dice, see [`crate::dice::items`].
+    pub item: Option<DiceItem>,
 }
 
 impl Default for Dice {
     fn default() -> Self {
         Self {
+            kind: DiceKind::D6,
             rolled: false,
-            since_landed: 0.0,
+            value: 0,
+            cocked: false,
+            still_frames: 0,
+            since_thrown: 0.0,
+            retries: 0,
+            speed: 0.0,
+            item: None,
         }
     }
 }
@@ -88,6 +162,22 @@ pub struct DiceBundle {
     pub velocity: Velocity,
     pub mass: AdditionalMassProperties,
     pub collision_events: ActiveEvents,
+    /// Kept up to date by Rapier, a sleeping die has settled.
+    pub sleeping: Sleeping,
+}
+
+impl DiceBundle {
+    /// `collider` should be the die's hull from [`crate::dice::DiceSet`].
+    pub fn new(kind: DiceKind, collider: Collider) -> Self {
+        Self {
+            dice: Dice {
+                kind,
+                ..Dice::default()
+            },
+            collider,
+            ..DiceBundle::default()
+        }
+    }
 }
 
 impl Default for DiceBundle {
@@ -99,6 +189,7 @@ impl Default for DiceBundle {
             velocity: Velocity::default(),
             mass: AdditionalMassProperties::Mass(9.0),
             collision_events: ActiveEvents::COLLISION_EVENTS,
+            sleeping: Sleeping::default(),
         }
     }
 }
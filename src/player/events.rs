@@ -11,22 +11,23 @@ use bevy::{
     transform::components::{GlobalTransform, Transform},
 };
 
+use bevy::prelude::{EventWriter, Query, With};
+
 use crate::{
-    components::{PlayerBundle, PlayerLight},
+    camera::PlayCameraSequenceEvent,
+    combat::{DeathEvent, Health},
+    components::{Player, PlayerBundle, PlayerLight, PLAYER_MAX_HEALTH, PLAYER_SPAWN_POSITION},
     player::systems::*,
+    AddUiMessageEvent,
 };
 
 #[derive(Event)]
 pub struct SpawnPlayerEvent;
 
-#[derive(Event)]
-pub struct DiceRollEvent {
-    pub position: Vec3,
-}
-
 pub(crate) fn spawn_player_listener(
     mut commands: Commands,
     mut events: EventReader<SpawnPlayerEvent>,
+    mut sequence_events: EventWriter<PlayCameraSequenceEvent>,
 ) {
     for ev in events.read() {
         println!("Spawned Player");
@@ -44,11 +45,17 @@ pub(crate) fn spawn_player_listener(
 
         commands
             .spawn(TransformBundle {
-                local: Transform::IDENTITY.with_translation(vec3(4.0, 5.0, 4.0)),
+                local: Transform::IDENTITY.with_translation(PLAYER_SPAWN_POSITION),
                 global: GlobalTransform::IDENTITY,
             })
             .insert(PlayerBundle::default());
 
+        sequence_events.send(PlayCameraSequenceEvent {
+            sequence: String::from("intro"),
+            target_position: PLAYER_SPAWN_POSITION,
+            scale: 1.0,
+        });
+
         // RPG light
         commands
             .spawn(PointLightBundle {
@@ -65,3 +72,23 @@ pub(crate) fn spawn_player_listener(
             .insert(PlayerLight);
     }
 }
+
+pub(crate) fn player_death_listener(
+    mut query: Query<(&mut Health, &mut Transform), With<Player>>,
+    mut events: EventReader<DeathEvent>,
+    mut add_message_event: EventWriter<AddUiMessageEvent>,
+) {
+    for ev in events.read() {
+        let Ok((mut health, mut xform)) = query.get_mut(ev.entity) else {
+            continue;
+        };
+
+        health.current = PLAYER_MAX_HEALTH;
+        xform.translation = PLAYER_SPAWN_POSITION;
+
+        add_message_event.send(AddUiMessageEvent {
+            message: String::from("You died!"),
+            duration: 3.0,
+        });
+    }
+}
@@ -9,13 +9,20 @@ use bevy_rapier3d::{
     geometry::{ActiveEvents, Collider},
     na::Dynamic,
     parry::query::point,
-    pipeline::CollisionEvent,
+    pipeline::{CollisionEvent, QueryFilter},
+    plugin::{RapierConfiguration, RapierContext},
     rapier::dynamics::BodyPair,
 };
 use rand::Rng;
 
 use crate::{
     camera::{CameraSceneParams, CameraState},
+    dice::{
+        self,
+        notation::{DiceExpr, DiceTerm},
+        DiceThrow, ExpressionRolledEvent, RollExpressionEvent, RollPurpose,
+    },
+    encounter::EncounterState,
     enemy::Enemy,
     mathx, AddUiMessageEvent, GameResourceHandles, LowResCamera, MainCamera, MaterialName,
     UserSettings,
@@ -58,6 +65,7 @@ pub fn move_player(
     user_cfg: Res<UserSettings>,
     mut gizmos: Gizmos,
     camera_state: Res<CameraState>,
+    encounter_state: Res<EncounterState>,
 ) {
     if query.is_empty() {
         return;
@@ -134,12 +142,16 @@ pub fn move_player(
         cam_xform.translation = eye.position;
     }
 
-    if !player.dice_active {
+    // Everyone stands still while an encounter plays out in turns
+    if !player.dice_active && encounter_state.encounter.is_none() {
         player.velocity = velocity;
-        controller.translation = Some(velocity);
+        // Physics runs at a fixed rate, so add up the frames since its last step
+        controller.translation = Some(controller.translation.unwrap_or(Vec3::ZERO) + velocity);
     }
 
-    if camera_state.scene_params.is_none() {
+    // Mouse motion still turns the eye during camera scenes, so the scene hands back the
+    // view the player has been steering towards.
+    {
         let mut mouse_delta = Vec2::ZERO;
         for ev in mouse_motion_events.read() {
             if cursor_unlocked {
@@ -159,7 +171,9 @@ pub fn move_player(
                 Quat::from_axis_angle(Vec3::Y, eye.yaw) * Quat::from_axis_angle(Vec3::X, eye.pitch);
         }
 
-        cam_xform.rotation = eye.view;
+        if camera_state.scene_params.is_none() {
+            cam_xform.rotation = eye.view;
+        }
     }
 
     // let start = cam_xform.translation - up * 1.5;
@@ -203,132 +217,206 @@ pub(crate) fn move_light(
     light.translation = Vec3::lerp(light.translation, target_pos, dt * 5.0);
 }
 
+/// Rolled with T, the classic way to roll up a character stat.
+const STAT_ROLL: &str = "4d6kh3";
+
+/// Holding Space this long throws as hard as possible.
+const FULL_CHARGE: f32 = 1.0;
+const MIN_THROW_SPEED: f32 = 1.5;
+const MAX_THROW_SPEED: f32 = 8.0;
+/// Spin added per pixel per second of mouse flick, capped at `MAX_THROW_SPIN`.
+const FLICK_SPIN: f32 = 0.01;
+const MAX_THROW_SPIN: f32 = 30.0;
+
 pub fn dice_system(
-    mut commands: Commands,
-    mut query: Query<(&Transform, &bevy_rapier3d::dynamics::Velocity, &mut Dice)>,
-    mut player_query: Query<(&mut Player, &Eye, &Transform), (With<Player>, Without<Dice>)>,
-    mut camera_query: Query<(&mut Transform), (With<LowResCamera>, Without<Player>, Without<Dice>)>,
+    mut player_query: Query<(Entity, &mut Player, &Eye, &mut Inventory)>,
     key: Res<ButtonInput<KeyCode>>,
-    resources: Res<GameResourceHandles>,
-    mut camera_state: ResMut<CameraState>,
     time: Res<Time>,
+    rapier_context: Res<RapierContext>,
+    rapier_config: Res<RapierConfiguration>,
+    mut gizmos: Gizmos,
+    mut mouse_motion_events: EventReader<MouseMotion>,
     mut add_message_event: EventWriter<AddUiMessageEvent>,
+    mut roll_events: EventWriter<RollExpressionEvent>,
 ) {
     if player_query.is_empty() {
         return;
     }
 
     let dt = time.delta_seconds();
-    let mut rng = rand::thread_rng();
-
-    let (mut player, eye, player_xform) = player_query.single_mut();
-    let mut cam_xform = camera_query.single_mut();
-    let fwd = eye.forward();
+    let (player_entity, mut player, eye, mut inventory) = player_query.single_mut();
 
-    // Roll the dice
-    if key.just_pressed(KeyCode::Space) && !player.dice_active {
-        let mut inherit_velocity = player.velocity;
-        inherit_velocity.y = 0.07;
-        let spawn_pos = eye.position + fwd * 1.5;
-        commands
-            .spawn(PbrBundle {
-                mesh: resources.dice_mesh.clone(),
-                material: resources.get_material(MaterialName::Dice),
-                transform: Transform::IDENTITY
-                    .with_translation(spawn_pos)
-                    .with_rotation(mathx::random::quat()),
-                ..default()
-            })
-            .insert(DiceBundle::default())
-            .insert(ExternalImpulse {
-                impulse: inherit_velocity * rng.gen_range(0.5..6.5) + fwd * 0.5,
-                torque_impulse: mathx::random::vec3() * rng.gen_range(0.1..0.3),
-            });
-        player.dice_active = true;
+    // Pick which dice to throw
+    let mut changed = false;
+    if key.just_pressed(KeyCode::KeyQ) {
+        player.dice_kind = player.dice_kind.next();
+        changed = true;
     }
-
-    if query.is_empty() {
-        return;
+    if key.just_pressed(KeyCode::Equal) && player.dice_count < MAX_DICE_POOL {
+        player.dice_count += 1;
+        changed = true;
+    }
+    if key.just_pressed(KeyCode::Minus) && player.dice_count > 1 {
+        player.dice_count -= 1;
+        changed = true;
     }
 
-    let mut get_dice_result = |xform: &Transform| -> i32 {
-        let dot_up = xform.up().dot(Vec3::Y);
-        let dot_down = xform.down().dot(Vec3::Y);
-        let dot_right = xform.right().dot(Vec3::Y);
-        let dot_left = xform.left().dot(Vec3::Y);
-        let dot_forward = xform.forward().dot(Vec3::Y);
-        let dot_backward = xform.back().dot(Vec3::Y);
-
-        if dot_up >= 0.5 {
-            return 1;
-        }
+    // G picks a special die to throw next, or goes back to the plain pool
+    if player
+        .dice_item
+        .map_or(false, |i| i >= inventory.dice_items.len())
+    {
+        player.dice_item = None;
+    }
+    if key.just_pressed(KeyCode::KeyG) {
+        player.dice_item = match player.dice_item {
+            None if !inventory.dice_items.is_empty() => Some(0),
+            Some(i) if i + 1 < inventory.dice_items.len() => Some(i + 1),
+            _ => None,
+        };
+        changed = true;
+    }
 
-        if dot_down >= 0.5 {
-            return 6;
-        }
+    let held_item = player.dice_item.map(|i| inventory.dice_items[i]);
+
+    if changed {
+        let message = match held_item {
+            Some(item) => format!("Holding the {}", item.def().name),
+            None => format!(
+                "Holding {}{} ({} carried)",
+                player.dice_count,
+                player.dice_kind.name(),
+                inventory.dice
+            ),
+        };
+        add_message_event.send(AddUiMessageEvent {
+            message,
+            duration: 1.5,
+        });
+    }
 
-        if dot_right >= 0.5 {
-            return 5;
-        }
+    // Smoothed over a few frames so a flick just before release still counts
+    let mouse_delta: Vec2 = mouse_motion_events.read().map(|ev| ev.delta).sum();
+    if dt > 0.0 {
+        let blend = (dt * 15.0).min(1.0);
+        player.throw_flick = player.throw_flick.lerp(mouse_delta / dt, blend);
+    }
 
-        if dot_left >= 0.5 {
-            return 2;
-        }
+    // Hold Space to charge an aimed throw, release to let go
+    if player.dice_active {
+        player.throw_charge = None;
+    } else if key.just_pressed(KeyCode::Space) {
+        player.throw_charge = Some(0.0);
+    }
 
-        if dot_forward >= 0.5 {
-            return 3;
-        }
+    let mut roll = None;
 
-        if dot_backward >= 0.5 {
-            return 4;
-        }
+    if let Some(charge) = player.throw_charge {
+        let charge = (charge + dt).min(FULL_CHARGE);
+        let throw = aimed_throw(eye, charge, player.throw_flick);
 
-        return 0;
-    };
+        if key.pressed(KeyCode::Space) {
+            player.throw_charge = Some(charge);
 
-    for (dice_xform, vel, mut dice) in query.iter_mut() {
-        if dice.rolled {
-            continue;
-        }
+            let filter = QueryFilter::default()
+                .exclude_collider(player_entity)
+                .exclude_sensors();
+            let (arc, landing) =
+                dice::throw_arc(&rapier_context, &throw, rapier_config.gravity, filter);
+            let color = Color::srgb(1.0, 1.0 - charge / FULL_CHARGE * 0.8, 0.2);
 
-        if vel.angvel.length() <= 0.1 || vel.linvel.length() <= 0.1 {
-            dice.since_landed += dt;
+            gizmos.linestrip(arc, color);
+            if let Some((point, normal)) = landing {
+                let normal = Dir3::new(normal).unwrap_or(Dir3::Y);
+                gizmos.circle(point + *normal * 0.01, normal, 0.25, color);
+            }
+        } else {
+            player.throw_charge = None;
+
+            let (count, kind) = match held_item {
+                Some(item) => (1, item.def().kind),
+                None => (player.dice_count, player.dice_kind),
+            };
+            let expression = DiceExpr::Dice(DiceTerm {
+                count,
+                sides: kind.sides(),
+                keep: None,
+                explode: None,
+                reroll: None,
+            });
+            roll = Some((expression, RollPurpose::Casual, Some(throw), held_item));
         }
+    } else if key.just_pressed(KeyCode::KeyT) {
+        roll = Some((
+            DiceExpr::parse(STAT_ROLL).unwrap(),
+            RollPurpose::Stat,
+            None,
+            None,
+        ));
+    }
 
-        if dice.since_landed >= 0.2 {
-            dice.rolled = true;
+    if let Some((expression, purpose, throw, item)) = roll {
+        // Thrown dice stay where they land until picked back up
+        let needed = match item {
+            Some(_) => 0,
+            None => expression.dice().len() as u32,
+        };
 
-            let result = get_dice_result(&dice_xform);
-            let message = format!("You rolled a {:?}!", result);
+        if needed > inventory.dice {
             add_message_event.send(AddUiMessageEvent {
-                message,
-                duration: 3.0,
+                message: format!("You need {} dice, you have {}", needed, inventory.dice),
+                duration: 1.5,
             });
-
-            cam_xform.translation =
-                dice_xform.translation + Vec3::Y * 0.9 + Vec3::X * 0.8 + Vec3::Z * 0.8;
-
-            let sl_pitch = mathx::f32::degrees_to_radians(-90.0);
-            commands.spawn(SpotLightBundle {
-                spot_light: SpotLight {
-                    color: Srgba::hex("#e6bfaa").unwrap().into(),
-                    intensity: 150_000.0,
-                    shadows_enabled: true,
-                    ..default()
-                },
-                transform: Transform::IDENTITY
-                    .with_translation(dice_xform.translation + Vec3::Y * 1.4)
-                    .with_rotation(Quat::from_euler(EulerRot::XYZ, sl_pitch, 0.0, 0.0)),
-                ..default()
+        } else if !player.dice_active {
+            inventory.dice -= needed;
+            if let Some(i) = player.dice_item.take().filter(|_| item.is_some()) {
+                inventory.dice_items.remove(i);
+            }
+            roll_events.send(RollExpressionEvent {
+                roller: player_entity,
+                expression,
+                purpose,
+                physical: true,
+                target: None,
+                show: true,
+                throw,
+                item,
             });
+            player.dice_active = true;
+        }
+    }
+}
 
-            camera_state.scene_params = Some(CameraSceneParams {
-                target_position: dice_xform.translation,
-                pos_offset: Vec3::Y * 1.2,
-                duration: 2.0,
-            });
+/// Frees the player once their dice settle. Runs even while throwing is off, at a table or
+/// in an encounter, so dice still rolling then can't leave the player stuck.
+pub fn dice_settled_listener(
+    mut player_query: Query<(Entity, &mut Player)>,
+    mut rolled_events: EventReader<ExpressionRolledEvent>,
+) {
+    let Ok((player_entity, mut player)) = player_query.get_single_mut() else {
+        return;
+    };
 
+    for ev in rolled_events.read() {
+        if ev.roller == player_entity {
             player.dice_active = false;
         }
     }
 }
+
+/// Throw along the view, harder the longer it was charged. Dice roll forward off the
+/// hand, flicking the mouse sideways adds spin and flicking up adds backspin.
+fn aimed_throw(eye: &Eye, charge: f32, flick: Vec2) -> DiceThrow {
+    let aim = *eye.forward();
+    let strength = charge / FULL_CHARGE;
+    let speed = MIN_THROW_SPEED + (MAX_THROW_SPEED - MIN_THROW_SPEED) * strength;
+
+    let tumble = Vec3::Y.cross(aim).normalize_or_zero();
+    let spin = tumble * speed * 2.0 + (tumble * flick.y - Vec3::Y * flick.x) * FLICK_SPIN;
+
+    DiceThrow {
+        origin: eye.position + aim * dice::THROW_REACH,
+        velocity: aim * speed,
+        spin: spin.clamp_length_max(MAX_THROW_SPIN),
+    }
+}
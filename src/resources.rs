@@ -15,7 +15,10 @@ use bevy::{
 use bevy_rapier3d::parry::partitioning;
 use bevy_sprite3d::Sprite3dParams;
 
-use crate::{enemy::EnemyKind, tilemap::TILE_SIZE, utils::ez_str};
+use crate::{
+    camera::effects::CameraEffectSettings, cards, enemy::EnemyKind, pickup::PickupIcon,
+    tilemap::TILE_SIZE, utils::ez_str, weapon::WeaponKind,
+};
 
 #[derive(Resource, Default, Deref, DerefMut)]
 pub struct CameraParameters(pub PhysicalCameraParameters);
@@ -41,6 +44,16 @@ pub struct GameResourceHandles {
     pub render_texture: Handle<Image>,
     pub font: Handle<Font>,
     pub enemy_sprites: HashMap<EnemyKind, Handle<Image>>,
+    pub weapon_materials: HashMap<WeaponKind, Handle<StandardMaterial>>,
+    pub viewmodel_quad: Handle<Mesh>,
+    pub projectile_mesh: Handle<Mesh>,
+    pub projectile_material: Handle<StandardMaterial>,
+    pub pickup_materials: HashMap<PickupIcon, Handle<StandardMaterial>>,
+    pub pickup_quad: Handle<Mesh>,
+    pub card_atlas: Handle<Image>,
+    pub card_layout: Handle<TextureAtlasLayout>,
+    pub gambler_material: Handle<StandardMaterial>,
+    pub gambler_quad: Handle<Mesh>,
 }
 
 impl GameResourceHandles {
@@ -61,6 +74,7 @@ pub(crate) fn load_resources(
     mut resources: ResMut<GameResourceHandles>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut images: ResMut<Assets<Image>>,
+    mut atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
 ) {
     let mut load_material = |name: MaterialName, image: String| {
         let texture_handle: Handle<Image> = assets.load(image);
@@ -85,6 +99,16 @@ pub(crate) fn load_resources(
 
     load_material(MaterialName::Dice, ez_str("cardsMedium_tilemap_packed.png"));
 
+    // Playing cards for the hand UI, one atlas cell per card
+    resources.card_atlas = assets.load("cardsMedium_tilemap_packed.png");
+    resources.card_layout = atlas_layouts.add(TextureAtlasLayout::from_grid(
+        UVec2::splat(cards::ATLAS_TILE),
+        cards::ATLAS_COLUMNS,
+        cards::ATLAS_ROWS,
+        None,
+        None,
+    ));
+
     // Meshes
     resources.cube = meshes.add(Cuboid {
         half_size: Vec3::splat(TILE_SIZE / 2.0),
@@ -98,8 +122,70 @@ pub(crate) fn load_resources(
     // TODO: Sprites
     let mut dict: HashMap<EnemyKind, Handle<Image>> = HashMap::new();
     dict.insert(EnemyKind::Skull, assets.load("enemy_sprites/skull.png"));
+    dict.insert(EnemyKind::Demon, assets.load("enemy_sprites/demon.png"));
+    dict.insert(EnemyKind::Ninja, assets.load("enemy_sprites/ninja.png"));
+    dict.insert(EnemyKind::Jack, assets.load("enemy_sprites/jack.png"));
+    dict.insert(EnemyKind::Agent, assets.load("enemy_sprites/agent.png"));
     resources.enemy_sprites = dict;
 
+    // Weapon viewmodels, drawn as unlit cutout quads in front of the low-res camera.
+    let mut load_weapon_material = |kind: WeaponKind, image: &str| {
+        let texture_handle: Handle<Image> = assets.load(String::from(image));
+
+        let added_material = assets.add(StandardMaterial {
+            base_color_texture: Some(texture_handle),
+            alpha_mode: AlphaMode::Mask(0.5),
+            unlit: true,
+            cull_mode: None,
+            ..default()
+        });
+
+        resources.weapon_materials.insert(kind, added_material);
+    };
+
+    load_weapon_material(WeaponKind::Dagger, "weapon_sprites/dagger.png");
+    load_weapon_material(WeaponKind::Axe, "weapon_sprites/axe.png");
+    load_weapon_material(WeaponKind::Crossbow, "weapon_sprites/crossbow.png");
+
+    // Pickup billboards
+    let mut load_pickup_material = |icon: PickupIcon, image: &str| {
+        let texture_handle: Handle<Image> = assets.load(String::from(image));
+
+        let added_material = assets.add(StandardMaterial {
+            base_color_texture: Some(texture_handle),
+            alpha_mode: AlphaMode::Mask(0.5),
+            unlit: true,
+            cull_mode: None,
+            ..default()
+        });
+
+        resources.pickup_materials.insert(icon, added_material);
+    };
+
+    load_pickup_material(PickupIcon::Potion, "pickup_sprites/potion.png");
+    load_pickup_material(PickupIcon::Coin, "pickup_sprites/coin.png");
+    load_pickup_material(PickupIcon::Key, "pickup_sprites/key.png");
+    load_pickup_material(PickupIcon::Die, "pickup_sprites/die.png");
+    load_pickup_material(PickupIcon::Gem, "pickup_sprites/gem.png");
+
+    resources.pickup_quad = meshes.add(Rectangle::new(0.6, 0.6));
+    resources.gambler_quad = meshes.add(Rectangle::new(1.0, 1.6));
+    resources.gambler_material = assets.add(StandardMaterial {
+        base_color: Color::srgb(1.0, 0.85, 0.6),
+        base_color_texture: Some(assets.load("enemy_sprites/agent.png")),
+        alpha_mode: AlphaMode::Mask(0.5),
+        unlit: true,
+        cull_mode: None,
+        ..default()
+    });
+    resources.viewmodel_quad = meshes.add(Rectangle::new(0.16, 0.24));
+    resources.projectile_mesh = meshes.add(Sphere::new(1.0));
+    resources.projectile_material = assets.add(StandardMaterial {
+        base_color: Color::srgb(0.9, 0.8, 0.6),
+        unlit: true,
+        ..default()
+    });
+
     {
         let size = Extent3d {
             width: 160 * 3,
@@ -132,4 +218,7 @@ pub(crate) fn load_resources(
 #[derive(Resource, Default)]
 pub struct UserSettings {
     pub mouse_sens: f32,
+    /// F7 lets the mouse turn the camera during camera scenes, not just the view they return to.
+    pub scene_look: bool,
+    pub camera_effects: CameraEffectSettings,
 }
@@ -0,0 +1,85 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+/// Independent random sequences, so e.g. an extra loot roll doesn't change the next dice throw.
+#[derive(Eq, PartialEq, Hash, Clone, Copy, Debug)]
+pub enum RngStream {
+    Dice,
+    Ai,
+    Loot,
+    MapGen,
+    Cards,
+    /// Damage and initiative rolled straight from the RNG, apart from the thrown dice.
+    Combat,
+    /// Looks only, like which way the camera kicks, so turning effects off keeps the game
+    /// the same.
+    Cosmetic,
+}
+
+impl RngStream {
+    pub const ALL: [RngStream; 7] = [
+        RngStream::Dice,
+        RngStream::Ai,
+        RngStream::Loot,
+        RngStream::MapGen,
+        RngStream::Cards,
+        RngStream::Combat,
+        RngStream::Cosmetic,
+    ];
+}
+
+/// Every random number in the game comes from here. The same seed and inputs
+/// give the same game, set it with the `SEED` environment variable.
+#[derive(Resource)]
+pub struct GameRng {
+    seed: u64,
+    /// ChaCha8 rather than `StdRng`, whose algorithm can change between rand releases.
+    streams: HashMap<RngStream, ChaCha8Rng>,
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        let streams = RngStream::ALL
+            .into_iter()
+            .enumerate()
+            .map(|(i, stream)| {
+                // Spread the streams apart so neighbouring seeds don't share sequences.
+                let stream_seed = seed ^ (i as u64 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+                (stream, ChaCha8Rng::seed_from_u64(stream_seed))
+            })
+            .collect();
+
+        Self { seed, streams }
+    }
+
+    /// Seed from `SEED`, or a fresh one that gets printed so the run can be replayed.
+    pub fn from_env() -> Self {
+        let seed = match std::env::var("SEED").ok().and_then(|s| s.parse().ok()) {
+            Some(seed) => seed,
+            None => rand::thread_rng().gen(),
+        };
+        println!("RNG seed: {}", seed);
+
+        Self::new(seed)
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Starts every stream over from `seed`.
+    pub fn reseed(&mut self, seed: u64) {
+        *self = Self::new(seed);
+    }
+
+    pub fn stream(&mut self, stream: RngStream) -> &mut ChaCha8Rng {
+        self.streams.get_mut(&stream).unwrap()
+    }
+}
+
+pub(crate) fn init(app: &mut App) {
+    app.insert_resource(GameRng::from_env());
+}
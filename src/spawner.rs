@@ -0,0 +1,427 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use rand::seq::SliceRandom;
+
+use crate::{
+    combat::{DamageEvent, Health},
+    dice::checks::{
+        CheckCriticalSuccessEvent, CheckFumbleEvent, CheckSuccessEvent, Skill, SkillCheckEvent,
+    },
+    encounter::exploring,
+    enemy::{EnemyKind, SpawnEnemyEvent},
+    pickup::INTERACT_DISTANCE,
+    player::components::{Eye, Player},
+    rng::{GameRng, RngStream},
+    tilemap::TILE_SIZE,
+    AddUiMessageEvent, GameResourceHandles, MaterialName,
+};
+
+#[derive(Clone, Debug)]
+pub struct SpawnEntry {
+    pub kind: EnemyKind,
+    pub weight: u32,
+}
+
+#[derive(Clone, Debug)]
+pub struct Wave {
+    pub count: u32,
+    pub table: Vec<SpawnEntry>,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum SpawnerState {
+    Dormant,
+    Active,
+    Cleared,
+}
+
+#[derive(Component, Clone)]
+pub struct EnemySpawner {
+    pub waves: Vec<Wave>,
+    /// Spawning pauses while this many enemies from this spawner are alive.
+    pub max_alive: u32,
+    /// Seconds between individual spawns.
+    pub cooldown: f32,
+    pub spawn_radius: f32,
+    /// The player has to enter this box, centred on the spawner, to start the encounter.
+    /// `None` starts it right away.
+    pub trigger_half_extents: Option<Vec3>,
+    /// Doors in this group open once every wave has been cleared.
+    pub door_group: Option<String>,
+    pub state: SpawnerState,
+    pub wave: usize,
+    pub spawned_in_wave: u32,
+    /// Spawn events sent but not yet turned into enemies.
+    pub pending: u32,
+    pub timer: f32,
+}
+
+impl Default for EnemySpawner {
+    fn default() -> Self {
+        Self {
+            waves: Vec::new(),
+            max_alive: 4,
+            cooldown: 1.0,
+            spawn_radius: 3.0,
+            trigger_half_extents: None,
+            door_group: None,
+            state: SpawnerState::Dormant,
+            wave: 0,
+            spawned_in_wave: 0,
+            pending: 0,
+            timer: 0.0,
+        }
+    }
+}
+
+impl EnemySpawner {
+    /// Parses a spawn table such as `Skull:2,Demon:1`, where the number is the weight.
+    pub fn parse_table(table: &str) -> Vec<SpawnEntry> {
+        table
+            .split(',')
+            .filter_map(|entry| {
+                let mut parts = entry.trim().split(':');
+                let kind = EnemyKind::from_name(parts.next()?.trim())?;
+                let weight = match parts.next() {
+                    Some(w) => w.trim().parse().ok()?,
+                    None => 1,
+                };
+
+                Some(SpawnEntry { kind, weight })
+            })
+            .collect()
+    }
+}
+
+/// Tags an enemy with the spawner that created it.
+#[derive(Component)]
+pub struct SpawnedBy(pub Entity);
+
+#[derive(Component)]
+pub struct Door {
+    pub group: String,
+    /// Can be picked open with a [`Skill::Lockpicking`] check against this DC.
+    pub lock_dc: Option<i32>,
+}
+
+#[derive(Event)]
+pub struct CreateSpawnerEvent {
+    pub position: Vec3,
+    pub spawner: EnemySpawner,
+}
+
+#[derive(Event)]
+pub struct CreateDoorEvent {
+    pub position: Vec3,
+    pub group: String,
+    pub lock_dc: Option<i32>,
+}
+
+#[derive(Event)]
+pub struct WaveStartedEvent {
+    pub spawner: Entity,
+    pub wave: usize,
+}
+
+/// Sent once every wave of a spawner has been spawned and killed.
+#[derive(Event)]
+pub struct EncounterClearedEvent {
+    pub spawner: Entity,
+    pub door_group: Option<String>,
+}
+
+pub(crate) fn init(app: &mut App) {
+    app.add_event::<CreateSpawnerEvent>();
+    app.add_event::<CreateDoorEvent>();
+    app.add_event::<WaveStartedEvent>();
+    app.add_event::<EncounterClearedEvent>();
+
+    app.add_systems(FixedFirst, (create_spawner_listener, create_door_listener));
+    app.add_systems(
+        Update,
+        (spawner_system.run_if(exploring), open_doors_listener).chain(),
+    );
+    app.add_systems(
+        Update,
+        (
+            pick_lock_system.run_if(exploring),
+            lockpick_outcome_listener,
+        ),
+    );
+}
+
+fn create_spawner_listener(mut commands: Commands, mut events: EventReader<CreateSpawnerEvent>) {
+    for ev in events.read() {
+        commands
+            .spawn(TransformBundle {
+                local: Transform::IDENTITY.with_translation(ev.position),
+                global: GlobalTransform::IDENTITY,
+            })
+            .insert(ev.spawner.clone());
+    }
+}
+
+fn create_door_listener(
+    mut commands: Commands,
+    resources: Res<GameResourceHandles>,
+    mut events: EventReader<CreateDoorEvent>,
+) {
+    for ev in events.read() {
+        commands
+            .spawn(PbrBundle {
+                mesh: resources.cube.clone(),
+                material: resources.get_material(MaterialName::Brick),
+                transform: Transform::IDENTITY.with_translation(ev.position),
+                ..default()
+            })
+            .insert(RigidBody::Fixed)
+            .insert(Collider::cuboid(
+                TILE_SIZE / 2.0,
+                TILE_SIZE / 2.0,
+                TILE_SIZE / 2.0,
+            ))
+            .insert(Door {
+                group: ev.group.clone(),
+                lock_dc: ev.lock_dc,
+            });
+    }
+}
+
+fn spawner_system(
+    mut query: Query<(Entity, &Transform, &mut EnemySpawner)>,
+    spawned_query: Query<(&SpawnedBy, &Health)>,
+    player_query: Query<&Transform, (With<Player>, Without<EnemySpawner>)>,
+    time: Res<Time>,
+    mut rng: ResMut<GameRng>,
+    mut spawn_events: EventWriter<SpawnEnemyEvent>,
+    mut wave_events: EventWriter<WaveStartedEvent>,
+    mut cleared_events: EventWriter<EncounterClearedEvent>,
+    mut add_message_event: EventWriter<AddUiMessageEvent>,
+) {
+    if player_query.is_empty() {
+        return;
+    }
+
+    let dt = time.delta_seconds();
+    let player_pos = player_query.single().translation;
+    let rng = rng.stream(RngStream::Ai);
+
+    let mut alive: HashMap<Entity, u32> = HashMap::new();
+    for (spawned_by, health) in spawned_query.iter() {
+        if !health.is_dead() {
+            *alive.entry(spawned_by.0).or_default() += 1;
+        }
+    }
+
+    for (entity, xform, mut spawner) in query.iter_mut() {
+        match spawner.state {
+            SpawnerState::Dormant => {
+                let triggered = match spawner.trigger_half_extents {
+                    Some(half_extents) => {
+                        let local = (player_pos - xform.translation).abs();
+                        local.x <= half_extents.x && local.z <= half_extents.z
+                    }
+                    None => true,
+                };
+
+                if triggered && !spawner.waves.is_empty() {
+                    spawner.state = SpawnerState::Active;
+                    spawner.wave = 0;
+                    spawner.spawned_in_wave = 0;
+                    spawner.timer = 0.0;
+
+                    wave_events.send(WaveStartedEvent {
+                        spawner: entity,
+                        wave: 0,
+                    });
+                    add_message_event.send(AddUiMessageEvent {
+                        message: String::from("Wave 1!"),
+                        duration: 2.0,
+                    });
+                }
+            }
+            SpawnerState::Active => {
+                let alive = alive.get(&entity).copied().unwrap_or(0);
+                let wave = spawner.waves[spawner.wave].clone();
+
+                spawner.timer -= dt;
+
+                if spawner.spawned_in_wave < wave.count
+                    && alive + spawner.pending < spawner.max_alive
+                    && spawner.timer <= 0.0
+                {
+                    let Ok(entry) = wave.table.choose_weighted(rng, |e| e.weight) else {
+                        // Nothing to spawn from an empty table, skip the wave.
+                        spawner.spawned_in_wave = wave.count;
+                        continue;
+                    };
+
+                    let offset = crate::mathx::random::vec2(rng) * spawner.spawn_radius;
+                    spawn_events.send(SpawnEnemyEvent {
+                        position: xform.translation + Vec3::new(offset.x, 0.0, offset.y),
+                        kind: entry.kind,
+                        spawner: Some(entity),
+                    });
+
+                    spawner.pending += 1;
+                    spawner.spawned_in_wave += 1;
+                    spawner.timer = spawner.cooldown;
+                }
+
+                let wave_done =
+                    spawner.spawned_in_wave >= wave.count && spawner.pending == 0 && alive == 0;
+
+                if !wave_done {
+                    continue;
+                }
+
+                if spawner.wave + 1 < spawner.waves.len() {
+                    spawner.wave += 1;
+                    spawner.spawned_in_wave = 0;
+                    spawner.timer = spawner.cooldown * 2.0;
+
+                    wave_events.send(WaveStartedEvent {
+                        spawner: entity,
+                        wave: spawner.wave,
+                    });
+                    add_message_event.send(AddUiMessageEvent {
+                        message: format!("Wave {}!", spawner.wave + 1),
+                        duration: 2.0,
+                    });
+                } else {
+                    spawner.state = SpawnerState::Cleared;
+
+                    cleared_events.send(EncounterClearedEvent {
+                        spawner: entity,
+                        door_group: spawner.door_group.clone(),
+                    });
+                    add_message_event.send(AddUiMessageEvent {
+                        message: String::from("The room falls silent."),
+                        duration: 3.0,
+                    });
+                }
+            }
+            SpawnerState::Cleared => {}
+        }
+    }
+}
+
+fn open_doors_listener(
+    mut commands: Commands,
+    query: Query<(Entity, &Door)>,
+    mut events: EventReader<EncounterClearedEvent>,
+    mut add_message_event: EventWriter<AddUiMessageEvent>,
+) {
+    for ev in events.read() {
+        let Some(group) = &ev.door_group else {
+            continue;
+        };
+
+        let mut opened = false;
+        for (entity, door) in query.iter() {
+            if &door.group == group {
+                commands.entity(entity).despawn_recursive();
+                opened = true;
+            }
+        }
+
+        if opened {
+            add_message_event.send(AddUiMessageEvent {
+                message: String::from("You hear a door grind open."),
+                duration: 3.0,
+            });
+        }
+    }
+}
+
+/// E on a locked door tries to pick it.
+fn pick_lock_system(
+    mut player_query: Query<(Entity, &Eye, &mut Player)>,
+    door_query: Query<&Door>,
+    rapier_context: Res<RapierContext>,
+    key: Res<ButtonInput<KeyCode>>,
+    mut check_events: EventWriter<SkillCheckEvent>,
+) {
+    if !key.just_pressed(KeyCode::KeyE) || player_query.is_empty() {
+        return;
+    }
+
+    let (player_entity, eye, mut player) = player_query.single_mut();
+    if player.dice_active {
+        return;
+    }
+
+    let filter = QueryFilter::default()
+        .exclude_collider(player_entity)
+        .exclude_sensors();
+    let Some((entity, _)) = rapier_context.cast_ray(
+        eye.position,
+        *eye.forward(),
+        INTERACT_DISTANCE,
+        true,
+        filter,
+    ) else {
+        return;
+    };
+    let Some(dc) = door_query.get(entity).ok().and_then(|door| door.lock_dc) else {
+        return;
+    };
+
+    check_events.send(SkillCheckEvent {
+        roller: player_entity,
+        skill: Skill::Lockpicking,
+        dc,
+        target: Some(entity),
+    });
+    player.dice_active = true;
+}
+
+/// Picked locks open, a fumble jams the lock and springs its needle.
+fn lockpick_outcome_listener(
+    mut commands: Commands,
+    mut door_query: Query<&mut Door>,
+    mut critical_events: EventReader<CheckCriticalSuccessEvent>,
+    mut success_events: EventReader<CheckSuccessEvent>,
+    mut fumble_events: EventReader<CheckFumbleEvent>,
+    mut damage_events: EventWriter<DamageEvent>,
+    mut add_message_event: EventWriter<AddUiMessageEvent>,
+) {
+    let successes = critical_events
+        .read()
+        .map(|ev| ev.0)
+        .chain(success_events.read().map(|ev| ev.0));
+
+    for result in successes.filter(|result| result.check.skill == Skill::Lockpicking) {
+        let Some(door) = result.check.target.filter(|e| door_query.contains(*e)) else {
+            continue;
+        };
+        commands.entity(door).despawn_recursive();
+        add_message_event.send(AddUiMessageEvent {
+            message: String::from("The lock clicks open."),
+            duration: 2.0,
+        });
+    }
+
+    for ev in fumble_events.read() {
+        if ev.0.check.skill != Skill::Lockpicking {
+            continue;
+        }
+        let Some(mut door) = ev.0.check.target.and_then(|e| door_query.get_mut(e).ok()) else {
+            continue;
+        };
+        door.lock_dc = None;
+        damage_events.send(DamageEvent {
+            target: ev.0.check.roller,
+            amount: 2,
+            source: ev.0.check.target,
+        });
+        add_message_event.send(AddUiMessageEvent {
+            message: String::from(
+                "Your pick snaps and a needle jabs your finger. The lock is jammed.",
+            ),
+            duration: 3.0,
+        });
+    }
+}
@@ -1,6 +1,8 @@
 use bevy::prelude::*;
 use bevy_sprite3d::{Sprite3d, Sprite3dParams};
 
+use crate::camera::LowResCamera;
+
 #[derive(Event)]
 pub struct CreateSprite3dEvent {
     pub entity: Entity,
@@ -8,9 +10,17 @@ pub struct CreateSprite3dEvent {
     pub image: Handle<Image>,
 }
 
+/// Turns a quad (facing +Z) around the Y axis so it always faces the low-res camera.
+#[derive(Component)]
+pub struct Billboard;
+
 pub(crate) fn init(mut app: &mut App) {
     app.add_event::<CreateSprite3dEvent>();
     app.add_systems(FixedFirst, create_sprite_listener);
+    app.add_systems(
+        PostUpdate,
+        billboard_system.before(TransformSystem::TransformPropagate),
+    );
 }
 
 fn create_sprite_listener(
@@ -32,3 +42,19 @@ fn create_sprite_listener(
         }
     }
 }
+
+fn billboard_system(
+    mut query: Query<(&mut Transform, &GlobalTransform), With<Billboard>>,
+    camera_query: Query<&Transform, (With<LowResCamera>, Without<Billboard>)>,
+) {
+    if camera_query.is_empty() {
+        return;
+    }
+
+    let camera_pos = camera_query.single().translation;
+
+    for (mut xform, global) in query.iter_mut() {
+        let to_camera = camera_pos - global.translation();
+        xform.rotation = Quat::from_rotation_y(to_camera.x.atan2(to_camera.z));
+    }
+}
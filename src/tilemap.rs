@@ -1,7 +1,12 @@
 use std::result;
 
 use crate::{
+    dice::notation::DiceExpr,
     enemy::{EnemyKind, SpawnEnemyEvent},
+    gambling::{CreateGamblerEvent, DiceGame},
+    pickup::{CreatePickupEvent, PickupKind},
+    rng::{GameRng, RngStream},
+    spawner::{CreateDoorEvent, CreateSpawnerEvent, EnemySpawner, Wave},
     utils::ez_str,
     MaterialName,
 };
@@ -13,13 +18,14 @@ use bevy::{
 };
 use bevy_rapier3d::prelude::*;
 use rand::distributions::Standard;
-use tiled::{FiniteTileLayer, Loader, Map};
+use tiled::{FiniteTileLayer, Loader, Map, ObjectShape, PropertyValue};
 
 use crate::GameResourceHandles;
 
 const FLOOR_LAYER: &str = "Floor";
 const WALL_LAYER: &str = "Wall";
 const CEILING_LAYER: &str = "Ceiling";
+const OBJECT_LAYER: &str = "Objects";
 
 const CHUNK_SIZE: i32 = 8;
 const TILE_SIZE_PIXELS: i32 = 16;
@@ -145,6 +151,141 @@ impl TileMap {
 
         result_layer
     }
+
+    /// Turns `Spawner`, `Door`, `Pickup` and `Gambler` objects into their entities.
+    fn process_object_layer(
+        map: &Map,
+        spawner_events: &mut EventWriter<CreateSpawnerEvent>,
+        door_events: &mut EventWriter<CreateDoorEvent>,
+        pickup_events: &mut EventWriter<CreatePickupEvent>,
+        gambler_events: &mut EventWriter<CreateGamblerEvent>,
+        rng: &mut GameRng,
+    ) {
+        let Some(layer) = map
+            .layers()
+            .find(|x| x.name == OBJECT_LAYER)
+            .and_then(|x| x.as_object_layer())
+        else {
+            return;
+        };
+
+        for object in layer.objects() {
+            let (width, height) = match object.shape {
+                ObjectShape::Rect { width, height } => (width, height),
+                _ => (0.0, 0.0),
+            };
+
+            let mut position =
+                TileMap::pixels_to_world(object.x + width / 2.0, object.y + height / 2.0);
+
+            let string_prop = |name: &str| match object.properties.get(name) {
+                Some(PropertyValue::StringValue(value)) => Some(value.clone()),
+                _ => None,
+            };
+
+            match object.user_type.as_str() {
+                "Spawner" => {
+                    let table =
+                        EnemySpawner::parse_table(&string_prop("enemies").unwrap_or_default());
+                    let waves = string_prop("waves")
+                        .unwrap_or_else(|| String::from("1"))
+                        .split(',')
+                        .filter_map(|count| count.trim().parse().ok())
+                        .map(|count| Wave {
+                            count,
+                            table: table.clone(),
+                        })
+                        .collect();
+
+                    let mut spawner = EnemySpawner {
+                        waves,
+                        door_group: string_prop("door"),
+                        ..default()
+                    };
+
+                    if let Some(PropertyValue::IntValue(max_alive)) =
+                        object.properties.get("max_alive")
+                    {
+                        spawner.max_alive = (*max_alive).max(0) as u32;
+                    }
+
+                    if let Some(PropertyValue::FloatValue(cooldown)) =
+                        object.properties.get("cooldown")
+                    {
+                        spawner.cooldown = *cooldown;
+                    }
+
+                    // The object's rectangle is the room that triggers the encounter.
+                    if width > 0.0 && height > 0.0 {
+                        let to_world = TILE_SIZE / TILE_SIZE_PIXELS as f32;
+                        let half_extents =
+                            vec3(width / 2.0 * to_world, TILE_SIZE, height / 2.0 * to_world);
+                        spawner.trigger_half_extents = Some(half_extents);
+                        spawner.spawn_radius = half_extents.x.min(half_extents.z) * 0.75;
+                    }
+
+                    position.y = 2.5;
+                    spawner_events.send(CreateSpawnerEvent { position, spawner });
+                }
+                "Door" => {
+                    position.y = TILE_SIZE / 2.0;
+                    let lock_dc = match object.properties.get("lock_dc") {
+                        Some(PropertyValue::IntValue(dc)) => Some(*dc),
+                        _ => None,
+                    };
+                    door_events.send(CreateDoorEvent {
+                        position,
+                        group: object.name.clone(),
+                        lock_dc,
+                    });
+                }
+                "Pickup" => {
+                    // Either a fixed number or a dice roll such as `3d6`.
+                    let amount = match object.properties.get("amount") {
+                        Some(PropertyValue::IntValue(amount)) => *amount,
+                        Some(PropertyValue::StringValue(roll)) => DiceExpr::parse(roll)
+                            .map(|expr| expr.roll(rng.stream(RngStream::MapGen)).total)
+                            .unwrap_or(1),
+                        _ => 1,
+                    };
+                    let auto_collect = match object.properties.get("auto_collect") {
+                        Some(PropertyValue::BoolValue(auto_collect)) => *auto_collect,
+                        _ => true,
+                    };
+
+                    let kind = string_prop("kind")
+                        .and_then(|kind| PickupKind::from_name(&kind, amount, &object.name));
+
+                    if let Some(kind) = kind {
+                        position.y = 0.6;
+                        pickup_events.send(CreatePickupEvent {
+                            position,
+                            kind,
+                            auto_collect,
+                        });
+                    }
+                }
+                "Gambler" => {
+                    let game = string_prop("game")
+                        .and_then(|game| DiceGame::from_name(&game))
+                        .unwrap_or(DiceGame::HighRoll);
+                    let purse = match object.properties.get("purse") {
+                        Some(PropertyValue::IntValue(purse)) => (*purse).max(0) as u32,
+                        _ => 50,
+                    };
+
+                    position.y = 0.8;
+                    gambler_events.send(CreateGamblerEvent {
+                        position,
+                        name: object.name.clone(),
+                        game,
+                        purse,
+                    });
+                }
+                _ => {}
+            }
+        }
+    }
 }
 
 // Event listeners
@@ -154,6 +295,11 @@ pub(crate) fn listen_create_tilemap(
     mut commands: Commands,
     resources: Res<GameResourceHandles>,
     mut spawn_tile_events: EventWriter<SpawnTileFromIdEvent>,
+    mut spawner_events: EventWriter<CreateSpawnerEvent>,
+    mut door_events: EventWriter<CreateDoorEvent>,
+    mut pickup_events: EventWriter<CreatePickupEvent>,
+    mut gambler_events: EventWriter<CreateGamblerEvent>,
+    mut rng: ResMut<GameRng>,
 ) {
     for ev in events.read() {
         let mut loader = Loader::new();
@@ -219,6 +365,15 @@ pub(crate) fn listen_create_tilemap(
                 .id();
         }
 
+        TileMap::process_object_layer(
+            &map,
+            &mut spawner_events,
+            &mut door_events,
+            &mut pickup_events,
+            &mut gambler_events,
+            &mut rng,
+        );
+
         commands.spawn_empty().insert(tm);
     }
 }
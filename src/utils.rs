@@ -1,3 +1,47 @@
 pub(crate) fn ez_str(str: &str) -> String {
     String::from(str)
 }
+
+/// Reads a data file, naming it in any error. `parse` gets the whole text.
+pub(crate) fn load_data<T>(
+    path: &str,
+    parse: impl FnOnce(&str) -> Result<T, String>,
+) -> Result<T, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    parse(&text).map_err(|e| format!("{}: {}", path, e))
+}
+
+/// Data files are read at startup and the game can't run without them.
+pub(crate) fn expect_data<T>(what: &str, loaded: Result<T, String>) -> T {
+    loaded.unwrap_or_else(|e| panic!("Can't read the {}: {}", what, e))
+}
+
+/// One line of a data file, split into words after its `#` comment is stripped.
+pub(crate) struct DataLine<'a> {
+    pub words: Vec<&'a str>,
+    number: usize,
+    text: &'a str,
+}
+
+impl DataLine<'_> {
+    pub fn error(&self) -> String {
+        format!("line {}: can't read `{}`", self.number + 1, self.text)
+    }
+
+    /// Parses one of the words as a number, or anything else `FromStr`.
+    pub fn parse<T: std::str::FromStr>(&self, word: &str) -> Result<T, String> {
+        word.parse().map_err(|_| self.error())
+    }
+}
+
+/// The lines of a data file that have something on them.
+pub(crate) fn data_lines(text: &str) -> impl Iterator<Item = DataLine<'_>> {
+    text.lines().enumerate().filter_map(|(number, line)| {
+        let text = line.split('#').next().unwrap().trim();
+        (!text.is_empty()).then(|| DataLine {
+            words: text.split_whitespace().collect(),
+            number,
+            text,
+        })
+    })
+}
@@ -0,0 +1,326 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use crate::{
+    camera::{effects::ViewPunchEvent, CameraState, LowResCamera},
+    combat::{spawn_projectile, DamageEvent, DamageRoll, Health},
+    encounter::exploring,
+    player::components::{CursorUnlocked, Eye, Player},
+    rng::{GameRng, RngStream},
+    GameResourceHandles,
+};
+
+#[derive(Eq, PartialEq, Hash, Clone, Copy, Debug)]
+pub enum WeaponKind {
+    Dagger,
+    Axe,
+    Crossbow,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum WeaponAttack {
+    /// Sweeps a ball of `radius` from the eye across `arc_degrees`, up to `reach` metres.
+    Melee {
+        reach: f32,
+        radius: f32,
+        arc_degrees: f32,
+    },
+    /// Fires a projectile entity.
+    Ranged {
+        projectile_speed: f32,
+        projectile_radius: f32,
+    },
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct WeaponDef {
+    pub name: &'static str,
+    pub attack: WeaponAttack,
+    pub cooldown: f32,
+    pub damage: DamageRoll,
+}
+
+impl WeaponKind {
+    pub const ALL: [WeaponKind; 3] = [WeaponKind::Dagger, WeaponKind::Axe, WeaponKind::Crossbow];
+
+    pub fn def(&self) -> WeaponDef {
+        match self {
+            WeaponKind::Dagger => WeaponDef {
+                name: "Dagger",
+                attack: WeaponAttack::Melee {
+                    reach: 1.6,
+                    radius: 0.25,
+                    arc_degrees: 50.0,
+                },
+                cooldown: 0.35,
+                damage: DamageRoll::new("1d4"),
+            },
+            WeaponKind::Axe => WeaponDef {
+                name: "Axe",
+                attack: WeaponAttack::Melee {
+                    reach: 2.0,
+                    radius: 0.35,
+                    arc_degrees: 100.0,
+                },
+                cooldown: 0.9,
+                damage: DamageRoll::new("1d8+1"),
+            },
+            WeaponKind::Crossbow => WeaponDef {
+                name: "Crossbow",
+                attack: WeaponAttack::Ranged {
+                    projectile_speed: 30.0,
+                    projectile_radius: 0.05,
+                },
+                cooldown: 1.2,
+                damage: DamageRoll::new("1d10"),
+            },
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct WeaponInventory {
+    pub weapons: Vec<WeaponKind>,
+    pub equipped: usize,
+    pub cooldown: f32,
+    /// Time since the last attack, drives the viewmodel swing/recoil.
+    pub since_attack: f32,
+}
+
+impl Default for WeaponInventory {
+    fn default() -> Self {
+        Self {
+            weapons: vec![WeaponKind::Dagger, WeaponKind::Axe, WeaponKind::Crossbow],
+            equipped: 0,
+            cooldown: 0.0,
+            since_attack: f32::MAX,
+        }
+    }
+}
+
+impl WeaponInventory {
+    pub fn current(&self) -> Option<WeaponKind> {
+        self.weapons.get(self.equipped).copied()
+    }
+}
+
+#[derive(Component)]
+pub struct WeaponViewModel {
+    pub kind: Option<WeaponKind>,
+}
+
+const VIEWMODEL_OFFSET: Vec3 = Vec3::new(0.22, -0.2, -0.45);
+const SWING_TIME: f32 = 0.2;
+
+pub(crate) fn init(app: &mut App) {
+    app.add_systems(
+        Update,
+        (
+            spawn_viewmodel,
+            weapon_system
+                .run_if(exploring)
+                .before(crate::combat::projectile_system),
+            viewmodel_system,
+        )
+            .chain(),
+    );
+}
+
+fn spawn_viewmodel(
+    mut commands: Commands,
+    query: Query<Entity, Added<WeaponInventory>>,
+    camera_query: Query<Entity, With<LowResCamera>>,
+    resources: Res<GameResourceHandles>,
+) {
+    if query.is_empty() || camera_query.is_empty() {
+        return;
+    }
+
+    let camera = camera_query.single();
+    let viewmodel = commands
+        .spawn(PbrBundle {
+            mesh: resources.viewmodel_quad.clone(),
+            transform: Transform::IDENTITY.with_translation(VIEWMODEL_OFFSET),
+            ..default()
+        })
+        .insert(WeaponViewModel { kind: None })
+        .insert(bevy::pbr::NotShadowCaster)
+        .id();
+
+    commands.entity(camera).add_child(viewmodel);
+}
+
+fn weapon_system(
+    mut commands: Commands,
+    mut query: Query<(Entity, &Eye, &mut WeaponInventory, Has<CursorUnlocked>), With<Player>>,
+    health_query: Query<(), With<Health>>,
+    rapier_context: Res<RapierContext>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    key: Res<ButtonInput<KeyCode>>,
+    camera_state: Res<CameraState>,
+    resources: Res<GameResourceHandles>,
+    time: Res<Time>,
+    mut rng: ResMut<GameRng>,
+    mut damage_events: EventWriter<DamageEvent>,
+    mut punch_events: EventWriter<ViewPunchEvent>,
+) {
+    if query.is_empty() {
+        return;
+    }
+
+    let dt = time.delta_seconds();
+    let (player_entity, eye, mut inventory, cursor_unlocked) = query.single_mut();
+
+    inventory.cooldown = (inventory.cooldown - dt).max(0.0);
+    inventory.since_attack += dt;
+
+    let slots = [KeyCode::Digit1, KeyCode::Digit2, KeyCode::Digit3];
+    for (i, slot) in slots.iter().enumerate() {
+        if key.just_pressed(*slot) && i < inventory.weapons.len() && i != inventory.equipped {
+            inventory.equipped = i;
+            inventory.cooldown = inventory.cooldown.max(0.25);
+        }
+    }
+
+    if cursor_unlocked || camera_state.scene_params.is_some() {
+        return;
+    }
+
+    if !mouse.pressed(MouseButton::Left) || inventory.cooldown > 0.0 {
+        return;
+    }
+
+    let Some(kind) = inventory.current() else {
+        return;
+    };
+
+    let def = kind.def();
+    inventory.cooldown = def.cooldown;
+    inventory.since_attack = 0.0;
+
+    let fwd = eye.forward();
+
+    match def.attack {
+        WeaponAttack::Melee {
+            reach,
+            radius,
+            arc_degrees,
+        } => {
+            let shape = Collider::ball(radius);
+            let filter = QueryFilter::default()
+                .exclude_collider(player_entity)
+                .exclude_sensors();
+
+            // Sample the swing arc with a handful of shape casts, sweeping right to left.
+            let samples = 5;
+            let half_arc = crate::mathx::f32::degrees_to_radians(arc_degrees) / 2.0;
+            // In sweep order, so the same seed hands out the same damage rolls
+            let mut hit = Vec::new();
+
+            for i in 0..samples {
+                let t = i as f32 / (samples - 1) as f32;
+                let angle = half_arc - t * half_arc * 2.0;
+                let dir = Quat::from_axis_angle(*eye.up(), angle) * *fwd;
+
+                if let Some((entity, _)) = rapier_context.cast_shape(
+                    eye.position,
+                    Quat::IDENTITY,
+                    dir,
+                    &shape,
+                    ShapeCastOptions::with_max_time_of_impact(reach),
+                    filter,
+                ) {
+                    if health_query.contains(entity) && !hit.contains(&entity) {
+                        hit.push(entity);
+                    }
+                }
+            }
+
+            // Follow the swing across, right to left
+            punch_events.send(ViewPunchEvent {
+                pitch: -1.0,
+                yaw: 2.0,
+            });
+
+            for entity in hit {
+                damage_events.send(DamageEvent {
+                    target: entity,
+                    amount: def.damage.roll(rng.stream(RngStream::Combat)),
+                    source: Some(player_entity),
+                });
+            }
+        }
+        WeaponAttack::Ranged {
+            projectile_speed,
+            projectile_radius,
+        } => {
+            punch_events.send(ViewPunchEvent {
+                pitch: 2.5,
+                yaw: 0.0,
+            });
+
+            spawn_projectile(
+                &mut commands,
+                &resources,
+                player_entity,
+                def.damage,
+                eye.position + fwd * 0.6,
+                fwd * projectile_speed,
+                projectile_radius,
+            );
+        }
+    }
+}
+
+fn viewmodel_system(
+    mut query: Query<(
+        &mut WeaponViewModel,
+        &mut Transform,
+        &mut Handle<StandardMaterial>,
+        &mut Visibility,
+    )>,
+    player_query: Query<&WeaponInventory, With<Player>>,
+    camera_state: Res<CameraState>,
+    resources: Res<GameResourceHandles>,
+) {
+    if query.is_empty() || player_query.is_empty() {
+        return;
+    }
+
+    let inventory = player_query.single();
+    let (mut viewmodel, mut xform, mut material, mut visibility) = query.single_mut();
+
+    let current = inventory.current();
+    if viewmodel.kind != current {
+        viewmodel.kind = current;
+
+        if let Some(kind) = current {
+            *material = resources.weapon_materials.get(&kind).unwrap().clone();
+        }
+    }
+
+    *visibility = if current.is_some() && camera_state.scene_params.is_none() {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    };
+
+    // Swing melee weapons across the view, kick ranged weapons back.
+    let t = (inventory.since_attack / SWING_TIME).min(1.0);
+    let phase = (t * std::f32::consts::PI).sin();
+
+    let (offset, rotation) = match current.map(|k| k.def().attack) {
+        Some(WeaponAttack::Melee { .. }) => (
+            Vec3::new(-0.15 * phase, 0.05 * phase, -0.1 * phase),
+            Quat::from_axis_angle(Vec3::Z, 1.2 * phase),
+        ),
+        Some(WeaponAttack::Ranged { .. }) => (
+            Vec3::new(0.0, 0.02 * phase, 0.08 * phase),
+            Quat::from_axis_angle(Vec3::X, 0.3 * phase),
+        ),
+        None => (Vec3::ZERO, Quat::IDENTITY),
+    };
+
+    xform.translation = VIEWMODEL_OFFSET + offset;
+    xform.rotation = rotation;
+}